@@ -0,0 +1,220 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::git::{get_repo_status, run_git, run_git_with_timeout};
+use crate::{ArchiveArgs, ScanOptions, UntrackedFiles};
+
+/// Append-only record of archived repositories, one JSON object per line.
+const ARCHIVE_LOG: &str = "archive.jsonl";
+
+#[derive(Debug, Serialize)]
+struct ArchiveRecord {
+    path: PathBuf,
+    archived_at: u64,
+    head: String,
+    remote_url: Option<String>,
+    bundle: Option<PathBuf>,
+}
+
+pub fn run(args: &ArchiveArgs, options: &ScanOptions) -> io::Result<()> {
+    let repo = fs::canonicalize(&args.repo)?;
+    // Git runs with `-C <repo>`, so a relative destination would land inside the repo.
+    let dest = resolve(&std::path::absolute(&args.dest)?)?;
+    if !repo.join(".git").exists() {
+        return Err(io::Error::other(format!(
+            "{} is not the root of a git repository",
+            repo.display()
+        )));
+    }
+    if dest.starts_with(&repo) {
+        return Err(io::Error::other(format!(
+            "{} is inside {}, which archiving removes; pick a destination outside it",
+            dest.display(),
+            repo.display()
+        )));
+    }
+
+    // Every untracked file, whatever `status.showUntrackedFiles` says: they all go.
+    let options = ScanOptions {
+        untracked: Some(UntrackedFiles::All),
+        ..options.clone()
+    };
+    let status = get_repo_status(&repo, &options).map_err(|err| io::Error::other(err.message))?;
+    let tracked_changes = status.uncommitted_changes - status.untracked_files;
+    if tracked_changes > 0 {
+        return Err(io::Error::other(format!(
+            "{} has {} uncommitted files; commit or stash them first",
+            repo.display(),
+            tracked_changes
+        )));
+    }
+    let ignored_files = count_ignored(&repo, &options)?;
+    if status.untracked_files > 0 || ignored_files > 0 {
+        let message = format!(
+            "{} has {} untracked and {} ignored files that no bundle keeps",
+            repo.display(),
+            status.untracked_files,
+            ignored_files
+        );
+        if !args.force {
+            return Err(io::Error::other(format!(
+                "{}; move them out or pass --force to delete them",
+                message
+            )));
+        }
+        eprintln!("warning: {}", message);
+    }
+
+    let unpushed = count_unpushed(&repo)?;
+    let unsafe_refs = unpushed_refs(&repo)?;
+    let archived_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let name = repo
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "repo".to_string());
+    let bundle = (unpushed > 0).then(|| dest.join(format!("{}-{}.bundle", name, archived_at)));
+
+    if unpushed > 0 {
+        println!("{} commits are on no remote", unpushed);
+    }
+    for reference in &unsafe_refs {
+        println!("not pushed: {}", reference);
+    }
+
+    if args.dry_run {
+        match &bundle {
+            Some(bundle) => println!("would bundle {} to {}", repo.display(), bundle.display()),
+            None => println!("{} is fully pushed", repo.display()),
+        }
+        println!("would remove {}", repo.display());
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dest)?;
+    if let Some(bundle) = &bundle {
+        let bundle_arg = bundle.to_string_lossy();
+        run_git(&repo, &["bundle", "create", &bundle_arg, "--all"])?;
+        run_git(&repo, &["bundle", "verify", &bundle_arg])?;
+        println!("bundled {} to {}", repo.display(), bundle.display());
+    }
+
+    let record = ArchiveRecord {
+        head: run_git(&repo, &["rev-parse", "HEAD"])
+            .map(|head| head.trim().to_string())
+            .unwrap_or_default(),
+        remote_url: remote_url(&repo),
+        path: repo.clone(),
+        archived_at,
+        bundle,
+    };
+    append_record(&dest, &record)?;
+
+    fs::remove_dir_all(&repo)?;
+    println!("archived {}", repo.display());
+    Ok(())
+}
+
+/// Files `.gitignore` keeps out of every status count, listed one by one.
+fn count_ignored(repo: &Path, options: &ScanOptions) -> io::Result<usize> {
+    let status = run_git_with_timeout(
+        repo,
+        &[
+            "status",
+            "--porcelain",
+            "--ignored",
+            "--untracked-files=all",
+        ],
+        options.timeout,
+    )?;
+    Ok(status
+        .lines()
+        .filter(|line| line.starts_with("!! "))
+        .count())
+}
+
+/// Commits reachable from any ref or HEAD, tags and the stash included, that no
+/// remote-tracking ref contains; anything above 0 needs a bundle.
+fn count_unpushed(repo: &Path) -> io::Result<usize> {
+    run_git(
+        repo,
+        &["rev-list", "--count", "--all", "--not", "--remotes"],
+    )?
+    .trim()
+    .parse()
+    .map_err(|err| io::Error::other(format!("unexpected rev-list output: {}", err)))
+}
+
+/// Names the refs behind the unpushed commits: local branches missing from, gone from
+/// or ahead of their upstream, plus the stash. Only for the report; the bundle decision
+/// rests on `count_unpushed`.
+fn unpushed_refs(repo: &Path) -> io::Result<Vec<String>> {
+    let refs = run_git(
+        repo,
+        &[
+            "for-each-ref",
+            "--format=%(refname:short)%09%(upstream)%09%(upstream:track)",
+            "refs/heads",
+        ],
+    )?;
+
+    let mut unsafe_refs = Vec::new();
+    for line in refs.lines() {
+        let mut fields = line.split('\t');
+        let branch = fields.next().unwrap_or_default();
+        let upstream = fields.next().unwrap_or_default();
+        let track = fields.next().unwrap_or_default();
+
+        if upstream.is_empty() {
+            unsafe_refs.push(format!("{} (no upstream)", branch));
+        } else if track.contains("gone") {
+            unsafe_refs.push(format!("{} (upstream gone)", branch));
+        } else if track.contains("ahead") {
+            unsafe_refs.push(format!("{} {}", branch, track));
+        }
+    }
+
+    if run_git(repo, &["rev-parse", "--verify", "--quiet", "refs/stash"]).is_ok() {
+        unsafe_refs.push("refs/stash".to_string());
+    }
+
+    Ok(unsafe_refs)
+}
+
+/// Resolves symlinks and `..` in the part of the path that exists, so a destination
+/// spelled through either still compares equal to the repo it would sit inside.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+            return Ok(path.to_path_buf());
+        };
+        missing.push(name);
+        existing = parent;
+    }
+    let mut resolved = fs::canonicalize(existing)?;
+    resolved.extend(missing.iter().rev());
+    Ok(resolved)
+}
+
+fn remote_url(repo: &Path) -> Option<String> {
+    run_git(repo, &["remote", "get-url", "origin"])
+        .ok()
+        .map(|url| url.trim().to_string())
+}
+
+fn append_record(dest: &Path, record: &ArchiveRecord) -> io::Result<()> {
+    let line = serde_json::to_string(record).map_err(io::Error::other)?;
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dest.join(ARCHIVE_LOG))?;
+    writeln!(log, "{}", line)
+}
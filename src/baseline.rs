@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::RepoStatus;
+
+/// The parts of a saved `--json` report that regressions and `diff` are measured against.
+#[derive(Debug, Deserialize)]
+pub struct Baseline {
+    repos: Vec<BaselineRepo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BaselineRepo {
+    path: PathBuf,
+    is_dirty: bool,
+    uncommitted_changes: usize,
+    unpushed_commits: usize,
+}
+
+/// A repo that is worse off than in the baseline.
+pub struct Regression<'a> {
+    pub status: &'a RepoStatus,
+    /// What got worse, e.g. `newly dirty` or `unpushed: 2 -> 5 commits`.
+    pub reasons: Vec<String>,
+}
+
+/// How one repository differs from the baseline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    /// Not in the baseline.
+    Added,
+    /// In the baseline but no longer found.
+    Removed,
+    NewlyDirty,
+    NewlyClean,
+    /// Had no unpushed commits in the baseline.
+    NewlyUnpushed,
+}
+
+impl ChangeKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::NewlyDirty => "newly dirty",
+            ChangeKind::NewlyClean => "newly clean",
+            ChangeKind::NewlyUnpushed => "newly unpushed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Change<'a> {
+    pub path: &'a Path,
+    pub change: ChangeKind,
+}
+
+pub fn load(path: &Path) -> io::Result<Baseline> {
+    let contents = fs::read_to_string(path)?;
+    serde_json::from_str(&contents).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid baseline {}: {}", path.display(), err),
+        )
+    })
+}
+
+impl Baseline {
+    /// Dirty repos that were clean or unknown before, and dirty repos whose counts grew.
+    /// Repos that got cleaner or disappeared are never regressions.
+    pub fn regressions<'a>(&self, statuses: &'a [RepoStatus]) -> Vec<Regression<'a>> {
+        let before: HashMap<&Path, &BaselineRepo> = self
+            .repos
+            .iter()
+            .map(|repo| (repo.path.as_path(), repo))
+            .collect();
+
+        let mut regressions = Vec::new();
+        for status in statuses.iter().filter(|status| status.is_dirty) {
+            let mut reasons = Vec::new();
+            match before.get(status.path.as_path()) {
+                None => reasons.push("new dirty repository".to_string()),
+                Some(old) if !old.is_dirty => reasons.push("newly dirty".to_string()),
+                Some(old) => {
+                    if status.uncommitted_changes > old.uncommitted_changes {
+                        reasons.push(format!(
+                            "uncommitted: {} -> {} files",
+                            old.uncommitted_changes, status.uncommitted_changes
+                        ));
+                    }
+                    if status.unpushed_commits > old.unpushed_commits {
+                        reasons.push(format!(
+                            "unpushed: {} -> {} commits",
+                            old.unpushed_commits, status.unpushed_commits
+                        ));
+                    }
+                }
+            }
+            if !reasons.is_empty() {
+                regressions.push(Regression { status, reasons });
+            }
+        }
+        regressions
+    }
+
+    /// Every repo that appeared, disappeared or changed between dirty, clean and unpushed,
+    /// in scan order with removed repos last. A repo can change in more than one way.
+    pub fn changes<'a>(&'a self, statuses: &'a [RepoStatus]) -> Vec<Change<'a>> {
+        let before: HashMap<&Path, &BaselineRepo> = self
+            .repos
+            .iter()
+            .map(|repo| (repo.path.as_path(), repo))
+            .collect();
+
+        let mut changes = Vec::new();
+        for status in statuses {
+            let path = status.path.as_path();
+            let Some(old) = before.get(path) else {
+                changes.push(Change {
+                    path,
+                    change: ChangeKind::Added,
+                });
+                continue;
+            };
+            if status.is_dirty != old.is_dirty {
+                let change = if status.is_dirty {
+                    ChangeKind::NewlyDirty
+                } else {
+                    ChangeKind::NewlyClean
+                };
+                changes.push(Change { path, change });
+            }
+            if status.unpushed_commits > 0 && old.unpushed_commits == 0 {
+                changes.push(Change {
+                    path,
+                    change: ChangeKind::NewlyUnpushed,
+                });
+            }
+        }
+
+        let current: HashSet<&Path> = statuses
+            .iter()
+            .map(|status| status.path.as_path())
+            .collect();
+        for repo in &self.repos {
+            if !current.contains(repo.path.as_path()) {
+                changes.push(Change {
+                    path: &repo.path,
+                    change: ChangeKind::Removed,
+                });
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::{VcsKind, empty_status};
+
+    /// A baseline as `--json` would have saved it.
+    fn baseline(repos: &[(&str, bool, usize, usize)]) -> Baseline {
+        Baseline {
+            repos: repos
+                .iter()
+                .map(
+                    |&(path, is_dirty, uncommitted_changes, unpushed_commits)| BaselineRepo {
+                        path: PathBuf::from(path),
+                        is_dirty,
+                        uncommitted_changes,
+                        unpushed_commits,
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    fn status(path: &str, uncommitted_changes: usize, unpushed_commits: usize) -> RepoStatus {
+        RepoStatus {
+            is_dirty: uncommitted_changes > 0 || unpushed_commits > 0,
+            uncommitted_changes,
+            unpushed_commits,
+            ..empty_status(Path::new(path), VcsKind::Git)
+        }
+    }
+
+    fn reasons<'a>(regressions: &'a [Regression]) -> Vec<(&'a Path, &'a [String])> {
+        regressions
+            .iter()
+            .map(|regression| (regression.status.path.as_path(), &regression.reasons[..]))
+            .collect()
+    }
+
+    #[test]
+    fn new_and_newly_dirty_repos_regress() {
+        let baseline = baseline(&[("clean", false, 0, 0)]);
+        let statuses = [status("clean", 1, 0), status("new", 2, 0)];
+        let regressions = baseline.regressions(&statuses);
+        assert_eq!(
+            reasons(&regressions),
+            [
+                (Path::new("clean"), &["newly dirty".to_string()][..]),
+                (Path::new("new"), &["new dirty repository".to_string()][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn growing_counts_regress() {
+        let baseline = baseline(&[("repo", true, 1, 2)]);
+        let statuses = [status("repo", 3, 5)];
+        let regressions = baseline.regressions(&statuses);
+        assert_eq!(
+            reasons(&regressions),
+            [(
+                Path::new("repo"),
+                &[
+                    "uncommitted: 1 -> 3 files".to_string(),
+                    "unpushed: 2 -> 5 commits".to_string(),
+                ][..]
+            )]
+        );
+    }
+
+    #[test]
+    fn unchanged_cleaner_and_removed_repos_do_not_regress() {
+        let baseline = baseline(&[
+            ("same", true, 2, 1),
+            ("cleaner", true, 5, 5),
+            ("gone", true, 1, 0),
+        ]);
+        let statuses = [
+            status("same", 2, 1),
+            status("cleaner", 1, 0),
+            status("clean", 0, 0),
+        ];
+        assert!(baseline.regressions(&statuses).is_empty());
+    }
+
+    #[test]
+    fn lists_every_kind_of_change() {
+        let baseline = baseline(&[
+            ("dirtied", false, 0, 0),
+            ("cleaned", true, 3, 0),
+            ("pushed-to", true, 1, 0),
+            ("same", true, 1, 1),
+            ("removed", false, 0, 0),
+        ]);
+        let statuses = [
+            status("dirtied", 1, 0),
+            status("cleaned", 0, 0),
+            status("pushed-to", 1, 2),
+            status("same", 4, 3),
+            status("added", 0, 0),
+        ];
+        let changes: Vec<(&Path, ChangeKind)> = baseline
+            .changes(&statuses)
+            .into_iter()
+            .map(|change| (change.path, change.change))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Path::new("dirtied"), ChangeKind::NewlyDirty),
+                (Path::new("cleaned"), ChangeKind::NewlyClean),
+                (Path::new("pushed-to"), ChangeKind::NewlyUnpushed),
+                (Path::new("added"), ChangeKind::Added),
+                (Path::new("removed"), ChangeKind::Removed),
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_scan_has_no_changes() {
+        let baseline = baseline(&[("dirty", true, 2, 1), ("clean", false, 0, 0)]);
+        let statuses = [status("dirty", 2, 1), status("clean", 0, 0)];
+        assert!(baseline.changes(&statuses).is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::TemplateConfig;
+use crate::git::{get_repo_status, run_git};
+use crate::policy::{PolicyEngine, PolicyViolation};
+use crate::{NewArgs, ScanOptions, manifest};
+
+pub fn run(
+    args: &NewArgs,
+    templates: &HashMap<String, TemplateConfig>,
+    policies: &PolicyEngine,
+) -> io::Result<()> {
+    let template = templates.get(&args.template).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no template `{}` in config", args.template),
+        )
+    })?;
+    let path = std::path::absolute(&args.path)?;
+    if path.exists() && fs::read_dir(&path)?.next().is_some() {
+        return Err(io::Error::other(format!(
+            "{} already exists and is not empty",
+            path.display()
+        )));
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::other(format!("{} has no folder name", path.display())))?;
+
+    // Checked before anything is written, so a template that breaks policy leaves no trace.
+    let mut violations: Vec<PolicyViolation> = Vec::new();
+    violations.extend(policies.branch_name_violation(&template.default_branch));
+    if !template.files.is_empty() {
+        violations.extend(policies.commit_message_violation(&template.initial_commit));
+    }
+    if !violations.is_empty() {
+        for violation in &violations {
+            println!("policy: {} ({})", violation.policy, violation.message);
+        }
+        return Err(io::Error::other(format!(
+            "template `{}` does not satisfy the configured policies",
+            args.template
+        )));
+    }
+
+    fs::create_dir_all(&path)?;
+    run_git(&path, &["init", "--quiet"])?;
+    let head = format!("refs/heads/{}", template.default_branch);
+    run_git(&path, &["symbolic-ref", "HEAD", &head])?;
+
+    for (file, contents) in &template.files {
+        let target = path.join(file);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(target, contents.replace("{name}", &name))?;
+    }
+    if let Some(hooks) = &template.hooks {
+        copy_hooks(hooks, &path.join(".git").join("hooks"))?;
+    }
+    if !template.files.is_empty() {
+        run_git(&path, &["add", "--all"])?;
+        run_git(
+            &path,
+            &["commit", "--quiet", "-m", &template.initial_commit],
+        )?;
+    }
+
+    let remote_url = template
+        .remote_url
+        .as_ref()
+        .map(|url| url.replace("{name}", &name));
+    if let Some(url) = &remote_url {
+        run_git(&path, &["remote", "add", &template.remote_name, url])?;
+    }
+    let manifest_path = manifest::register(&path, remote_url.as_deref())?;
+
+    let options = ScanOptions::default();
+    let mut status =
+        get_repo_status(&path, &options).map_err(|err| io::Error::other(err.message))?;
+    policies.apply(std::slice::from_mut(&mut status), options.timeout);
+    for violation in &status.policy_violations {
+        println!("policy: {} ({})", violation.policy, violation.message);
+    }
+
+    println!(
+        "created: {} (template {}, branch {}{})",
+        path.display(),
+        args.template,
+        template.default_branch,
+        remote_url
+            .map(|url| format!(", {} {}", template.remote_name, url))
+            .unwrap_or_default()
+    );
+    println!("registered in {}", manifest_path.display());
+    Ok(())
+}
+
+/// Copies hook scripts, keeping their permissions so they stay executable.
+fn copy_hooks(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use regex::Regex;
+
+use crate::config::CheckConfig;
+use crate::git::{get_repo_status, run_git, unpushed_subjects};
+use crate::policy::PolicyEngine;
+use crate::redact::Redactor;
+use crate::{CheckArgs, RepoStatus, ScanOptions};
+
+/// An unpushed commit whose subject matches a forbidden pattern.
+struct Violation {
+    commit: String,
+    subject: String,
+    pattern: String,
+}
+
+pub fn run(
+    args: &CheckArgs,
+    config: &CheckConfig,
+    policies: &PolicyEngine,
+    options: &ScanOptions,
+    redactor: Option<&Redactor>,
+) -> io::Result<()> {
+    let forbidden = compile_patterns(&config.forbidden_subjects)?;
+    let toplevel = run_git(&args.repo, &["rev-parse", "--show-toplevel"])?;
+    let repo = Path::new(toplevel.trim());
+    let mut status = get_repo_status(repo, options).map_err(|err| io::Error::other(err.message))?;
+    policies.apply(std::slice::from_mut(&mut status), options.timeout);
+    let mut violations = forbidden_subjects(&status, &forbidden, options.timeout)?;
+    let has_problems = !violations.is_empty() || !status.policy_violations.is_empty();
+    if let Some(redactor) = redactor {
+        redactor.redact_status(&mut status);
+        for violation in &mut violations {
+            violation.subject = redactor.redact_subject(&violation.subject);
+        }
+    }
+
+    if args.pre_commit {
+        // Hook output sits in the middle of a commit or push, so only problems are shown.
+        for violation in &violations {
+            println!(
+                "gittracker: {} \"{}\" has a forbidden subject (matches `{}`)",
+                violation.commit, violation.subject, violation.pattern
+            );
+        }
+        for violation in &status.policy_violations {
+            println!("gittracker: {}: {}", violation.policy, violation.message);
+        }
+        if has_problems {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if status.is_dirty {
+        println!(
+            "dirty: {} (uncommitted: {} files, unpushed: {} commits)",
+            status.path.display(),
+            status.uncommitted_changes,
+            status.unpushed_commits
+        );
+    } else {
+        println!("clean: {}", status.path.display());
+    }
+    for violation in &violations {
+        println!(
+            "forbidden subject: {} \"{}\" (matches `{}`)",
+            violation.commit, violation.subject, violation.pattern
+        );
+    }
+    for violation in &status.policy_violations {
+        println!("policy: {}: {}", violation.policy, violation.message);
+    }
+    if status.is_dirty || has_problems {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn compile_patterns(patterns: &[String]) -> io::Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid forbidden subject pattern `{}`: {}", pattern, err),
+                )
+            })
+        })
+        .collect()
+}
+
+fn forbidden_subjects(
+    status: &RepoStatus,
+    forbidden: &[Regex],
+    timeout: Option<Duration>,
+) -> io::Result<Vec<Violation>> {
+    if forbidden.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut violations = Vec::new();
+    for (commit, subject) in unpushed_subjects(&status.path, status.has_upstream, timeout)? {
+        let Some(pattern) = forbidden.iter().find(|pattern| pattern.is_match(&subject)) else {
+            continue;
+        };
+        violations.push(Violation {
+            commit,
+            subject,
+            pattern: pattern.as_str().to_string(),
+        });
+    }
+    Ok(violations)
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::config::config_dir;
+use crate::git::output_with_input;
+use crate::{RepoStatus, pool};
+
+/// Folder next to `config.toml` whose executables run once per repository.
+const CHECKS_DIR: &str = "checks.d";
+
+/// What a check prints on stdout; printing nothing means it found nothing.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CheckOutput {
+    /// Shown with the repo and added to its JSON under `findings.<check name>`.
+    findings: BTreeMap<String, serde_json::Value>,
+    /// Overrides whether the repo counts as dirty.
+    dirty: Option<bool>,
+}
+
+/// Runs every executable in `checks.d` against every repo. Each gets the repo's absolute
+/// path as its argument and working directory and the repo's status as JSON on stdin.
+/// A check that fails or prints something other than a JSON object is skipped with a
+/// warning; later checks see neither its findings nor its verdict.
+pub fn run(statuses: &mut [RepoStatus], timeout: Option<Duration>) {
+    let checks = match list_checks() {
+        Ok(checks) => checks,
+        Err(err) => {
+            eprintln!("warning: failed to list checks: {}", err);
+            return;
+        }
+    };
+    if checks.is_empty() {
+        return;
+    }
+
+    let results = pool::map(statuses, pool::default_workers(), |status| {
+        // Repos reached through `--docker`/`--ssh` aren't on this machine.
+        if !status.path.is_dir() {
+            return Vec::new();
+        }
+        let Ok(input) = serde_json::to_vec(status) else {
+            return Vec::new();
+        };
+        let repo = std::path::absolute(&status.path).unwrap_or_else(|_| status.path.clone());
+        checks
+            .iter()
+            .filter_map(
+                |(name, check)| match run_check(check, &repo, input.clone(), timeout) {
+                    Ok(output) => Some((name.clone(), output)),
+                    Err(err) => {
+                        eprintln!(
+                            "warning: check {} failed for {}: {}",
+                            name,
+                            status.path.display(),
+                            err
+                        );
+                        None
+                    }
+                },
+            )
+            .collect::<Vec<_>>()
+    });
+
+    for (status, outputs) in statuses.iter_mut().zip(results) {
+        for (name, output) in outputs {
+            if let Some(is_dirty) = output.dirty {
+                status.is_dirty = is_dirty;
+            }
+            if !output.findings.is_empty() {
+                status.findings.insert(name, output.findings);
+            }
+        }
+    }
+}
+
+/// Executable files in `checks.d`, by name; a missing folder means no checks.
+fn list_checks() -> io::Result<Vec<(String, PathBuf)>> {
+    let Some(dir) = config_dir().map(|dir| dir.join(CHECKS_DIR)) else {
+        return Ok(Vec::new());
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut checks = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if !is_executable(&path) {
+            continue;
+        }
+        let Some(name) = path.file_stem() else {
+            continue;
+        };
+        checks.push((name.to_string_lossy().into_owned(), path));
+    }
+    checks.sort();
+    Ok(checks)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn run_check(
+    check: &Path,
+    repo: &Path,
+    input: Vec<u8>,
+    timeout: Option<Duration>,
+) -> io::Result<CheckOutput> {
+    debug!(check = %check.display(), repo = %repo.display(), "running check");
+    let mut command = Command::new(check);
+    command.arg(repo).current_dir(repo);
+    let output = output_with_input(command, Some(input), timeout)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "exited with {}: {}",
+            output.status,
+            stderr.trim()
+        )));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(CheckOutput::default());
+    }
+    serde_json::from_str(&stdout).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid output: {}", err),
+        )
+    })
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+/// Folder names sync clients create, matched against every ancestor of a repo.
+const SYNC_FOLDERS: [(&str, &str); 7] = [
+    ("Dropbox", "Dropbox"),
+    ("OneDrive", "OneDrive"),
+    ("Google Drive", "Google Drive"),
+    ("My Drive", "Google Drive"),
+    ("GoogleDrive", "Google Drive"),
+    ("iCloud Drive", "iCloud"),
+    ("Mobile Documents", "iCloud"),
+];
+
+/// Files a sync client leaves in the root of the folder it manages.
+const SYNC_MARKERS: [(&str, &str); 2] = [(".dropbox", "Dropbox"), (".dropbox.cache", "Dropbox")];
+
+/// The sync service whose folder contains the repo, guessed from folder names such as
+/// `Dropbox` or `OneDrive - Acme` and from marker files like `.dropbox`.
+pub fn sync_service(repo: &Path) -> Option<&'static str> {
+    let repo = std::path::absolute(repo).ok()?;
+    for dir in repo.ancestors() {
+        if let Some(name) = dir.file_name().and_then(|name| name.to_str()) {
+            // macOS keeps File Provider folders as `~/Library/CloudStorage/GoogleDrive-me@x`.
+            let name = name.split(['-', '@']).next().unwrap_or(name).trim();
+            if let Some((_, service)) = SYNC_FOLDERS.iter().find(|(folder, _)| *folder == name) {
+                return Some(service);
+            }
+        }
+        if let Some((_, service)) = SYNC_MARKERS
+            .iter()
+            .find(|(marker, _)| dir.join(marker).exists())
+        {
+            return Some(service);
+        }
+    }
+    None
+}
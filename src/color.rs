@@ -0,0 +1,109 @@
+use std::io::IsTerminal;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color terminal output unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Colors of the human report, from the `[theme]` section of `config.toml`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub dirty: Style,
+    /// The `CONFLICTS:` label of repos with unmerged paths.
+    pub conflicts: Style,
+    pub unpushed: Style,
+    pub clean: Style,
+    pub no_remote: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            dirty: Style::ansi("31"),
+            conflicts: Style::ansi("1;31"),
+            unpushed: Style::ansi("33"),
+            clean: Style::ansi("32"),
+            no_remote: Style::ansi("35"),
+        }
+    }
+}
+
+/// Space-separated color and attribute names, e.g. `bold red` or `bright-yellow`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Style {
+    /// SGR parameters, e.g. `1;31`; empty for no styling.
+    codes: String,
+}
+
+impl Style {
+    fn ansi(codes: &str) -> Self {
+        Style {
+            codes: codes.to_string(),
+        }
+    }
+
+    pub fn paint(&self, text: &str) -> String {
+        if self.codes.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", self.codes, text)
+    }
+}
+
+impl TryFrom<String> for Style {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        const COLORS: [&str; 8] = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+        ];
+        let mut codes = Vec::new();
+        for word in value.split_whitespace() {
+            let code = match word {
+                "none" => continue,
+                "bold" => 1,
+                "dim" => 2,
+                "italic" => 3,
+                "underline" => 4,
+                _ => {
+                    let (base, name) = match word.strip_prefix("bright-") {
+                        Some(name) => (90, name),
+                        None => (30, word),
+                    };
+                    let index = COLORS
+                        .iter()
+                        .position(|color| *color == name)
+                        .ok_or_else(|| format!("unknown color or attribute `{}`", word))?;
+                    base + index
+                }
+            };
+            codes.push(code.to_string());
+        }
+        Ok(Style {
+            codes: codes.join(";"),
+        })
+    }
+}
+
+/// Whether a report written to stdout (or to a file, when `is_stdout` is false) gets
+/// colors. `--color always` beats `NO_COLOR`; files are only colored when asked for.
+pub fn is_enabled(choice: ColorChoice, is_stdout: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            is_stdout
+                && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && std::io::stdout().is_terminal()
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+use tracing::debug;
+
+use crate::color::Theme;
+use crate::sink::SinkSpec;
+
+/// Settings read from `config.toml`; every section is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub webhooks: Vec<WebhookConfig>,
+    /// Where reports go when `--sink` is not given, e.g. `["human", "json:report.json"]`.
+    pub sinks: Vec<SinkSpec>,
+    pub theme: Theme,
+    pub watch: WatchConfig,
+    pub check: CheckConfig,
+    pub policy: PolicyConfig,
+    pub score: ScoreConfig,
+    pub enrich: EnrichConfig,
+    pub templates: HashMap<String, TemplateConfig>,
+    /// Exceptions for single repositories, keyed by path, e.g. `[repos."~/work/legacy"]`.
+    pub repos: BTreeMap<String, RepoConfig>,
+}
+
+/// How one repository is treated; by default exactly like every other.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RepoConfig {
+    /// Leave the repository out of every report.
+    pub ignore: bool,
+    /// Untracked files neither count as uncommitted changes nor make the repo dirty.
+    pub allow_untracked: bool,
+    /// Uncommitted files tolerated before the repo counts as dirty.
+    pub max_uncommitted: usize,
+    /// Unpushed commits tolerated before the repo counts as dirty.
+    pub max_unpushed: usize,
+}
+
+/// Blueprint for `new --template <name>`; `{name}` expands to the new folder's name.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TemplateConfig {
+    pub default_branch: String,
+    pub remote_name: String,
+    /// e.g. `git@github.com:acme/{name}.git`
+    pub remote_url: Option<String>,
+    /// Folder whose files are copied into `.git/hooks`.
+    pub hooks: Option<PathBuf>,
+    /// Initial files by relative path; committed when there are any.
+    pub files: BTreeMap<String, String>,
+    pub initial_commit: String,
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        TemplateConfig {
+            default_branch: "main".to_string(),
+            remote_name: "origin".to_string(),
+            remote_url: None,
+            hooks: None,
+            files: BTreeMap::new(),
+            initial_commit: "Initial commit".to_string(),
+        }
+    }
+}
+
+/// Rules evaluated against every scanned repository.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Regexes of which the current branch must match at least one; empty allows any name.
+    pub branch_names: Vec<String>,
+    /// Lint for unpushed commit subjects: `conventional` or a custom regex.
+    pub commit_messages: Option<String>,
+    /// Treat repos inside Dropbox, OneDrive, Google Drive or iCloud folders as violations
+    /// rather than only warning about them.
+    pub forbid_cloud_sync: bool,
+}
+
+/// Weights of the hygiene `score`; each factor adds its weight once per unit.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ScoreConfig {
+    /// Per uncommitted file.
+    pub uncommitted: f64,
+    /// Per unpushed commit.
+    pub unpushed: f64,
+    /// Per stash entry.
+    pub stashes: f64,
+    /// Per day since the repo was last touched, while it has work that could be lost.
+    pub stale_days: f64,
+    /// Once, when nothing upstream tracks the current branch.
+    pub no_upstream: f64,
+}
+
+impl Default for ScoreConfig {
+    fn default() -> Self {
+        ScoreConfig {
+            uncommitted: 1.0,
+            unpushed: 2.0,
+            stashes: 1.0,
+            stale_days: 0.1,
+            no_upstream: 5.0,
+        }
+    }
+}
+
+/// Policies enforced by `check`, typically run from a pre-commit hook.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CheckConfig {
+    /// Regexes that unpushed commit subjects must not match.
+    pub forbidden_subjects: Vec<String>,
+}
+
+impl Default for CheckConfig {
+    fn default() -> Self {
+        CheckConfig {
+            forbidden_subjects: vec![
+                r"(?i)^wip\b".to_string(),
+                "^fixup! ".to_string(),
+                "^squash! ".to_string(),
+            ],
+        }
+    }
+}
+
+/// Which hosts `--enrich` may send API tokens to.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct EnrichConfig {
+    /// Self-hosted GitLab instances besides gitlab.com, e.g. `["git.example.com"]`.
+    pub gitlab_hosts: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Only send when at least this many repos are dirty.
+    pub min_dirty: Option<usize>,
+    /// Only send when at least this many repos have unpushed commits.
+    pub min_unpushed: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    #[default]
+    Json,
+    Slack,
+}
+
+/// Tuning for `watch`, so busy build folders don't trigger constant re-checks.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    /// Gitignore-style patterns, relative to the watched root, whose events are dropped.
+    pub exclude: Vec<String>,
+    /// Quiet period after the last event before affected repos are checked.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub debounce: Duration,
+    /// Minimum time between two checks of the same repository.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub cooldown: Duration,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            exclude: Vec::new(),
+            debounce: Duration::from_millis(500),
+            cooldown: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Accepts the same `500ms` / `2s` / `5m` forms as the command line.
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    crate::parse_duration(&value).map_err(serde::de::Error::custom)
+}
+
+/// Loads the given file, or the default location if it exists.
+pub fn load(path: Option<&Path>) -> io::Result<Config> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => match default_path() {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(Config::default()),
+        },
+    };
+
+    debug!(path = %path.display(), "loading config");
+    let contents = fs::read_to_string(&path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+    toml::from_str(&contents).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid config {}: {}", path.display(), err),
+        )
+    })
+}
+
+/// `$XDG_CONFIG_HOME/gittracker`, falling back to `~/.config/gittracker`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("gittracker"))
+}
+
+fn default_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
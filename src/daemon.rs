@@ -0,0 +1,357 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use crate::config::{Config, config_dir};
+use crate::events::{build_exclusions, relevant_paths};
+use crate::manifest::canonical;
+use crate::notify::Notifier;
+use crate::policy::PolicyEngine;
+use crate::redact::Redactor;
+use crate::{
+    DaemonArgs, JsonOutput, SCHEMA_VERSION, ScanOptions, ScanResult, StatusArgs, apply_config,
+    manifest, scan_root,
+};
+
+/// File in the config directory that `daemon` writes and `status --cached` reads.
+const STATE_FILE: &str = "daemon-state.json";
+
+/// The latest scan, as the daemon left it on disk.
+#[derive(Serialize, Deserialize)]
+struct State {
+    root: PathBuf,
+    /// Whether the daemon ran with `--redact`, which also hashes `root`.
+    #[serde(default)]
+    redacted: bool,
+    /// When the scan finished, in seconds since the Unix epoch.
+    scanned_at: u64,
+    duration_secs: f64,
+    /// The `--json` report of the scan, with clean repos included, exactly as printed.
+    report: Box<RawValue>,
+}
+
+/// The parts of a report that `status` prints.
+#[derive(Deserialize)]
+struct StatusReport {
+    repos: Vec<StatusRepo>,
+    errors: Vec<StatusError>,
+}
+
+#[derive(Deserialize)]
+struct StatusRepo {
+    path: PathBuf,
+    is_dirty: bool,
+    uncommitted_changes: usize,
+    conflicts: usize,
+    unpushed_commits: usize,
+}
+
+#[derive(Deserialize)]
+struct StatusError {
+    path: PathBuf,
+    message: String,
+}
+
+/// Scans the root on every interval and, with `--on-change`, once files under it settle
+/// after a change, replacing the state file after each scan. Runs until killed.
+pub fn run(
+    args: &DaemonArgs,
+    config: &Config,
+    policies: &PolicyEngine,
+    options: &ScanOptions,
+    redactor: Option<&Redactor>,
+) -> io::Result<()> {
+    if args.interval.is_zero() && !args.on_change {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--interval 0 needs --on-change, or nothing would ever trigger a scan",
+        ));
+    }
+    let path = state_path(args.state.as_deref())?;
+    let absolute_root = std::path::absolute(&args.root)?;
+    let exclusions = build_exclusions(&absolute_root, &config.watch.exclude)?;
+
+    // The watcher holds the sender even when unused, so waiting simply times out.
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+    if args.on_change {
+        watcher
+            .watch(&absolute_root, RecursiveMode::Recursive)
+            .map_err(io::Error::other)?;
+    }
+
+    let mut notifier = args.notify.then(|| Notifier::new(args.notify_stale_after));
+    let mut last_scan = Instant::now();
+    save_scan(
+        &path,
+        &args.root,
+        config,
+        policies,
+        options,
+        notifier.as_mut(),
+        redactor,
+    )?;
+    eprintln!(
+        "scanned {}, results in {}",
+        args.root.display(),
+        path.display()
+    );
+
+    let mut last_event: Option<Instant> = None;
+    loop {
+        let until_interval =
+            (!args.interval.is_zero()).then(|| args.interval.saturating_sub(last_scan.elapsed()));
+        // A burst of changes waits for quiet, and scans stay a cooldown apart.
+        let until_settled = last_event.map(|last| {
+            config
+                .watch
+                .debounce
+                .saturating_sub(last.elapsed())
+                .max(config.watch.cooldown.saturating_sub(last_scan.elapsed()))
+        });
+        let received = match until_interval.into_iter().chain(until_settled).min() {
+            Some(wait) => events.recv_timeout(wait),
+            None => events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(Ok(event)) => {
+                if relevant_paths(&event, &absolute_root, &exclusions)
+                    .next()
+                    .is_some()
+                {
+                    last_event = Some(Instant::now());
+                }
+            }
+            Ok(Err(err)) => eprintln!("error: watch failed: {}", err),
+            Err(RecvTimeoutError::Timeout) => {
+                last_scan = Instant::now();
+                last_event = None;
+                // A failed write leaves the previous results in place for the next try.
+                if let Err(err) = save_scan(
+                    &path,
+                    &args.root,
+                    config,
+                    policies,
+                    options,
+                    notifier.as_mut(),
+                    redactor,
+                ) {
+                    eprintln!("error: failed to save results: {}", err);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// Prints the dirty repos, from a fresh scan or with `--cached` from the daemon's latest
+/// one; exits 1 when any is dirty, like a plain scan.
+pub fn status(
+    args: &StatusArgs,
+    config: &Config,
+    policies: &PolicyEngine,
+    options: &ScanOptions,
+    redactor: Option<&Redactor>,
+) -> io::Result<()> {
+    let state = if args.cached {
+        let path = state_path(args.state.as_deref())?;
+        let state = load(&path)?;
+        // The roots only compare when both sides are hashed with the same salt.
+        let root = match (state.redacted, redactor) {
+            (false, None) => canonical(&args.root),
+            (true, Some(redactor)) => redactor.redact_path(&canonical(&args.root)),
+            (true, None) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the cached results are redacted; pass --redact with the daemon's salt",
+                ));
+            }
+            (false, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the cached results aren't redacted; restart the daemon with --redact",
+                ));
+            }
+        };
+        if state.root != root {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "no cached results for {}; the daemon scans {}",
+                    args.root.display(),
+                    state.root.display()
+                ),
+            ));
+        }
+        state
+    } else {
+        scan(&args.root, config, policies, options, None, redactor)?
+    };
+
+    let report: StatusReport = serde_json::from_str(state.report.get()).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid report: {}", err),
+        )
+    })?;
+    if args.json {
+        println!("{}", state.report.get());
+    } else {
+        print_status(&report, &state, args);
+    }
+
+    if report.repos.iter().any(|repo| repo.is_dirty) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_status(report: &StatusReport, state: &State, args: &StatusArgs) {
+    for error in &report.errors {
+        eprintln!("error: {}: {}", error.path.display(), error.message);
+    }
+    for repo in &report.repos {
+        if !repo.is_dirty && !args.show_clean {
+            continue;
+        }
+        let label = match (repo.is_dirty, repo.conflicts > 0) {
+            (true, true) => "CONFLICTS",
+            (true, false) => "dirty",
+            (false, _) => "clean",
+        };
+        println!(
+            "{}: {} (uncommitted: {} files, unpushed: {} commits)",
+            label,
+            repo.path.display(),
+            repo.uncommitted_changes,
+            repo.unpushed_commits
+        );
+    }
+
+    let dirty = report.repos.iter().filter(|repo| repo.is_dirty).count();
+    println!("scanned {} repositories", report.repos.len());
+    println!("dirty: {}, clean: {}", dirty, report.repos.len() - dirty);
+    if args.cached {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        println!(
+            "cached: scanned {}s ago in {:.1}s",
+            now.saturating_sub(state.scanned_at),
+            state.duration_secs
+        );
+    }
+}
+
+/// Scans the root the way a plain run would, minus the optional extras.
+fn scan(
+    root: &Path,
+    config: &Config,
+    policies: &PolicyEngine,
+    options: &ScanOptions,
+    notifier: Option<&mut Notifier>,
+    redactor: Option<&Redactor>,
+) -> io::Result<State> {
+    let started = Instant::now();
+    let mut scan = scan_root(root, options);
+    apply_config(&mut scan.statuses, config, policies, options, true);
+    if let Some(notifier) = notifier {
+        notifier.observe(&scan.statuses);
+    }
+    if let Err(err) = manifest::attach_metadata(&mut scan.statuses) {
+        eprintln!("warning: failed to read repository metadata: {}", err);
+    }
+    // Redacted before it reaches the state file, which other users may be able to read.
+    if let Some(redactor) = redactor {
+        redactor.redact_scan(&mut scan);
+    }
+
+    let ScanResult {
+        statuses,
+        errors,
+        unscanned,
+        ..
+    } = &scan;
+    let report = JsonOutput {
+        schema_version: SCHEMA_VERSION,
+        total: statuses.len(),
+        repos: statuses,
+        errors,
+        unscanned,
+        sample: None,
+        top: None,
+    };
+    let root = canonical(root);
+    Ok(State {
+        root: match redactor {
+            Some(redactor) => redactor.redact_path(&root),
+            None => root,
+        },
+        redacted: redactor.is_some(),
+        scanned_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        duration_secs: started.elapsed().as_secs_f64(),
+        report: serde_json::to_string_pretty(&report)
+            .and_then(RawValue::from_string)
+            .map_err(io::Error::other)?,
+    })
+}
+
+fn save_scan(
+    path: &Path,
+    root: &Path,
+    config: &Config,
+    policies: &PolicyEngine,
+    options: &ScanOptions,
+    notifier: Option<&mut Notifier>,
+    redactor: Option<&Redactor>,
+) -> io::Result<()> {
+    let state = scan(root, config, policies, options, notifier, redactor)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Written next to the state file and renamed over it, so `status --cached` never
+    // reads half a report.
+    let temporary = path.with_extension("json.tmp");
+    let contents = serde_json::to_string(&state).map_err(io::Error::other)?;
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+fn load(path: &Path) -> io::Result<State> {
+    let contents = fs::read_to_string(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!(
+                "no cached results in {} ({}); is `gittracker daemon` running?",
+                path.display(),
+                err
+            ),
+        )
+    })?;
+    serde_json::from_str(&contents).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid state file {}: {}", path.display(), err),
+        )
+    })
+}
+
+fn state_path(state: Option<&Path>) -> io::Result<PathBuf> {
+    match state {
+        Some(path) => Ok(path.to_path_buf()),
+        None => config_dir().map(|dir| dir.join(STATE_FILE)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no config directory; pass --state FILE",
+            )
+        }),
+    }
+}
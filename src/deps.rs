@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::RepoStatus;
+use crate::git::{normalize_url, remote_urls, run_git};
+
+/// A git dependency pinned to a commit that the scanned dependency repo has moved past.
+#[derive(Clone, Debug, Serialize)]
+pub struct DependencyDrift {
+    /// Package name as the lock file records it.
+    pub name: String,
+    /// The scanned repository the dependency comes from.
+    pub repo: PathBuf,
+    pub pinned: String,
+    /// Commits on the dependency repo's HEAD that the pin lacks; `None` when the pinned
+    /// commit is not in the local clone.
+    pub behind: Option<usize>,
+}
+
+/// A git-sourced package read from a lock file.
+struct GitPin {
+    name: String,
+    url: String,
+    commit: String,
+}
+
+#[derive(Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<CargoPackage>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    name: String,
+    source: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PackageLock {
+    #[serde(default)]
+    packages: HashMap<String, NodePackage>,
+}
+
+#[derive(Deserialize)]
+struct NodePackage {
+    resolved: Option<String>,
+}
+
+/// Compares git pins in `Cargo.lock` and `package-lock.json` against the HEAD of the
+/// scanned repo they point at. Path dependencies build from the dependency's working
+/// tree, so only git pins can fall behind.
+pub fn check(statuses: &mut [RepoStatus]) {
+    let mut by_url: HashMap<String, PathBuf> = HashMap::new();
+    for status in statuses.iter() {
+        for url in remote_urls(&status.path) {
+            by_url.insert(normalize_url(&url), status.path.clone());
+        }
+    }
+    if by_url.is_empty() {
+        return;
+    }
+
+    for status in statuses.iter_mut() {
+        for pin in lock_file_pins(&status.path) {
+            let Some(repo) = by_url.get(&normalize_url(&pin.url)) else {
+                continue;
+            };
+            if *repo == status.path {
+                continue;
+            }
+            let range = format!("{}..HEAD", pin.commit);
+            let behind = run_git(repo, &["rev-list", "--count", &range])
+                .ok()
+                .and_then(|count| count.trim().parse().ok());
+            if behind == Some(0) {
+                continue;
+            }
+            status.dependency_drift.push(DependencyDrift {
+                name: pin.name,
+                repo: repo.clone(),
+                pinned: pin.commit,
+                behind,
+            });
+        }
+    }
+}
+
+fn lock_file_pins(repo: &Path) -> Vec<GitPin> {
+    let mut pins = Vec::new();
+
+    if let Ok(contents) = fs::read_to_string(repo.join("Cargo.lock"))
+        && let Ok(lock) = toml::from_str::<CargoLock>(&contents)
+    {
+        // `git+https://host/repo?rev=...#<commit>`
+        for package in lock.package {
+            let Some(source) = package.source.as_deref() else {
+                continue;
+            };
+            let Some((url, commit)) = source.strip_prefix("git+").and_then(|s| s.split_once('#'))
+            else {
+                continue;
+            };
+            pins.push(GitPin {
+                name: package.name,
+                url: url.to_string(),
+                commit: commit.to_string(),
+            });
+        }
+    }
+
+    if let Ok(contents) = fs::read_to_string(repo.join("package-lock.json"))
+        && let Ok(lock) = serde_json::from_str::<PackageLock>(&contents)
+    {
+        // `git+ssh://git@host/repo.git#<commit>`, keyed by `node_modules/<name>`.
+        for (key, package) in lock.packages {
+            let Some(resolved) = package.resolved.as_deref() else {
+                continue;
+            };
+            let Some((url, commit)) = resolved
+                .strip_prefix("git+")
+                .and_then(|s| s.split_once('#'))
+            else {
+                continue;
+            };
+            let name = key.rsplit("node_modules/").next().unwrap_or(&key);
+            pins.push(GitPin {
+                name: name.to_string(),
+                url: url.to_string(),
+                commit: commit.to_string(),
+            });
+        }
+    }
+
+    pins.sort_by(|a, b| a.name.cmp(&b.name));
+    pins
+}
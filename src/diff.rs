@@ -0,0 +1,58 @@
+use std::io;
+
+use serde::Serialize;
+
+use crate::baseline::{self, Change};
+use crate::config::Config;
+use crate::policy::PolicyEngine;
+use crate::redact::Redactor;
+use crate::{DiffArgs, ScanOptions, apply_config, scan_root};
+
+#[derive(Serialize)]
+struct DiffOutput<'a> {
+    changes: &'a [Change<'a>],
+}
+
+/// Scans the root and prints how it differs from the saved report; exits 1 when
+/// anything changed, like `diff`.
+pub fn run(
+    args: &DiffArgs,
+    config: &Config,
+    policies: &PolicyEngine,
+    options: &ScanOptions,
+    redactor: Option<&Redactor>,
+) -> io::Result<()> {
+    let baseline = baseline::load(&args.baseline)
+        .map_err(|err| io::Error::new(err.kind(), format!("failed to load baseline: {}", err)))?;
+    let mut scan = scan_root(&args.root, options);
+    apply_config(&mut scan.statuses, config, policies, options, true);
+    // A baseline saved with `--redact` holds hashed paths, so compare like with like.
+    if let Some(redactor) = redactor {
+        redactor.redact_scan(&mut scan);
+    }
+    for error in &scan.errors {
+        eprintln!("error: {}: {}", error.path.display(), error.message);
+    }
+
+    let changes = baseline.changes(&scan.statuses);
+    if args.json {
+        let output = DiffOutput { changes: &changes };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output).map_err(io::Error::other)?
+        );
+    } else {
+        for change in &changes {
+            println!("{}: {}", change.change.label(), change.path.display());
+        }
+        match changes.len() {
+            0 => println!("no changes since {}", args.baseline.display()),
+            count => println!("{} changes since {}", count, args.baseline.display()),
+        }
+    }
+
+    if !changes.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::RepoStatus;
+use crate::config::EnrichConfig;
+use crate::forge::{Forge, ForgeKind, OpenRequest, RemoteState};
+use crate::pool;
+
+/// What the hosting provider says about a repo's remote and current branch.
+#[derive(Clone, Debug, Serialize)]
+pub struct Enrichment {
+    pub remote_state: RemoteState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_request: Option<OpenRequest>,
+}
+
+/// Looks up every repo whose main remote is on github.com, gitlab.com or a GitLab host
+/// listed in `[enrich] gitlab_hosts`. Tokens come from `GITHUB_TOKEN` or `GITLAB_TOKEN`,
+/// falling back to `GITTRACKER_FORGE_TOKEN`; hosts without a token are skipped with a
+/// warning, and no other host ever sees one.
+pub fn enrich(statuses: &mut [RepoStatus], config: &EnrichConfig) {
+    let mut forges: HashMap<String, Option<Forge>> = HashMap::new();
+    for status in statuses.iter() {
+        let Some(host) = status
+            .remote
+            .as_ref()
+            .and_then(|remote| remote.host.as_deref())
+        else {
+            continue;
+        };
+        let host = host.to_lowercase();
+        if forges.contains_key(&host) {
+            continue;
+        }
+        let forge = forge_for(&host, config);
+        forges.insert(host, forge);
+    }
+
+    let results = pool::map(statuses, pool::default_workers(), |status| {
+        let remote = status.remote.as_ref()?;
+        let host = remote.host.as_deref()?.to_lowercase();
+        let full_name = remote.full_name.as_deref()?;
+        let forge = forges.get(&host)?.as_ref()?;
+        Some(
+            lookup(forge, full_name, status.branch.as_deref()).map_err(|err| {
+                eprintln!("warning: {}: {}", status.path.display(), err);
+            }),
+        )
+    });
+    for (status, result) in statuses.iter_mut().zip(results) {
+        status.enrichment = result.and_then(Result::ok);
+    }
+}
+
+fn forge_for(host: &str, config: &EnrichConfig) -> Option<Forge> {
+    let is_gitlab = host == "gitlab.com"
+        || config
+            .gitlab_hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host));
+    let (kind, api_url, token_var) = if host == "github.com" {
+        (ForgeKind::Github, None, "GITHUB_TOKEN")
+    } else if is_gitlab {
+        let api_url = format!("https://{}/api/v4", host);
+        (ForgeKind::Gitlab, Some(api_url), "GITLAB_TOKEN")
+    } else {
+        // Anyone can name a host after GitLab, so it gets no token until configured.
+        if host.contains("gitlab") {
+            eprintln!(
+                "warning: skipping {} repositories; add the host to [enrich] gitlab_hosts in config.toml to enrich them",
+                host
+            );
+        }
+        return None;
+    };
+
+    let token = [token_var, "GITTRACKER_FORGE_TOKEN"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().filter(|token| !token.is_empty()));
+    let Some(token) = token else {
+        eprintln!(
+            "warning: skipping {} repositories; set {} to enrich them",
+            host, token_var
+        );
+        return None;
+    };
+    Some(Forge::new(kind, api_url.as_deref(), &token))
+}
+
+fn lookup(forge: &Forge, full_name: &str, branch: Option<&str>) -> std::io::Result<Enrichment> {
+    let remote_state = forge.repo_state(full_name)?;
+    let open_request = match branch {
+        Some(branch) if remote_state != RemoteState::Deleted => {
+            forge.open_request(full_name, branch)?
+        }
+        _ => None,
+    };
+    Ok(Enrichment {
+        remote_state,
+        open_request,
+    })
+}
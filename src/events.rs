@@ -0,0 +1,59 @@
+use std::io;
+use std::path::{Component, Path};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{Event, EventKind};
+
+use crate::vcs;
+
+/// Gitignore-style `[watch] exclude` patterns, relative to the watched root.
+pub fn build_exclusions(root: &Path, patterns: &[String]) -> io::Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        builder.add_line(None, pattern).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid watch exclusion `{}`: {}", pattern, err),
+            )
+        })?;
+    }
+    builder.build().map_err(io::Error::other)
+}
+
+/// The event's paths, relative to the root, that may change what a scan reports.
+/// Reads never do, nor does anything excluded. Inside `.git`, `.jj` and `.hg` only
+/// moved refs count: everything else there is bookkeeping, including the index that
+/// every `git status` refreshes, which would otherwise trigger the next check forever.
+pub fn relevant_paths<'a>(
+    event: &'a Event,
+    absolute_root: &'a Path,
+    exclusions: &'a Gitignore,
+) -> impl Iterator<Item = &'a Path> {
+    let is_access = matches!(event.kind, EventKind::Access(_));
+    event
+        .paths
+        .iter()
+        .filter(move |_| !is_access)
+        .filter_map(move |path| {
+            let relative = path.strip_prefix(absolute_root).ok()?;
+            let is_excluded = exclusions
+                .matched_path_or_any_parents(relative, path.is_dir())
+                .is_ignore();
+            (!is_excluded && !is_vcs_bookkeeping(relative)).then_some(relative)
+        })
+}
+
+fn is_vcs_bookkeeping(relative: &Path) -> bool {
+    let mut components = relative.components();
+    while let Some(component) = components.next() {
+        if let Component::Normal(name) = component
+            && vcs::is_marker_name(name)
+        {
+            let inside = components.as_path();
+            return !(inside == Path::new("HEAD")
+                || inside == Path::new("packed-refs")
+                || inside.starts_with("refs"));
+        }
+    }
+    false
+}
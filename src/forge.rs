@@ -0,0 +1,248 @@
+use std::io;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Page size both GitHub and GitLab accept as their maximum.
+const PER_PAGE: usize = 100;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ForgeKind {
+    #[default]
+    Github,
+    Gitlab,
+}
+
+/// A repository as the forge lists it.
+#[derive(Clone, Debug)]
+pub struct ForgeRepo {
+    /// `owner/name`, or the full namespace path on GitLab.
+    pub full_name: String,
+    pub ssh_url: String,
+    pub https_url: String,
+    pub is_archived: bool,
+}
+
+/// Whether the repository still exists on the forge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteState {
+    Active,
+    Archived,
+    /// Gone, or hidden from the token.
+    Deleted,
+}
+
+/// An open pull request (GitHub) or merge request (GitLab).
+#[derive(Clone, Debug, Serialize)]
+pub struct OpenRequest {
+    pub number: u64,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct GithubRepo {
+    full_name: String,
+    ssh_url: String,
+    clone_url: String,
+    #[serde(default)]
+    archived: bool,
+}
+
+#[derive(Deserialize)]
+struct GithubPull {
+    number: u64,
+    html_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitlabMergeRequest {
+    iid: u64,
+    web_url: String,
+}
+
+#[derive(Deserialize)]
+struct Archived {
+    #[serde(default)]
+    archived: bool,
+}
+
+#[derive(Deserialize)]
+struct GitlabProject {
+    path_with_namespace: String,
+    ssh_url_to_repo: String,
+    http_url_to_repo: String,
+    #[serde(default)]
+    archived: bool,
+}
+
+pub struct Forge {
+    kind: ForgeKind,
+    api_url: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl Forge {
+    /// `api_url` defaults to the public github.com / gitlab.com APIs.
+    pub fn new(kind: ForgeKind, api_url: Option<&str>, token: &str) -> Self {
+        let default_url = match kind {
+            ForgeKind::Github => "https://api.github.com",
+            ForgeKind::Gitlab => "https://gitlab.com/api/v4",
+        };
+        Forge {
+            kind,
+            api_url: api_url
+                .unwrap_or(default_url)
+                .trim_end_matches('/')
+                .to_string(),
+            token: token.to_string(),
+            agent: ureq::Agent::new_with_config(
+                ureq::config::Config::builder()
+                    .timeout_global(Some(Duration::from_secs(30)))
+                    .build(),
+            ),
+        }
+    }
+
+    /// Every repository in the organization (GitHub) or group and its subgroups (GitLab).
+    pub fn org_repos(&self, org: &str) -> io::Result<Vec<ForgeRepo>> {
+        let mut repos = Vec::new();
+        for page in 1.. {
+            let batch = match self.kind {
+                ForgeKind::Github => {
+                    let url = format!(
+                        "{}/orgs/{}/repos?per_page={}&page={}",
+                        self.api_url, org, PER_PAGE, page
+                    );
+                    let listed: Vec<GithubRepo> = serde_json::from_str(&self.get(&url)?)?;
+                    listed
+                        .into_iter()
+                        .map(|repo| ForgeRepo {
+                            full_name: repo.full_name,
+                            ssh_url: repo.ssh_url,
+                            https_url: repo.clone_url,
+                            is_archived: repo.archived,
+                        })
+                        .collect::<Vec<_>>()
+                }
+                ForgeKind::Gitlab => {
+                    let url = format!(
+                        "{}/groups/{}/projects?include_subgroups=true&per_page={}&page={}",
+                        self.api_url,
+                        encode(org),
+                        PER_PAGE,
+                        page
+                    );
+                    let listed: Vec<GitlabProject> = serde_json::from_str(&self.get(&url)?)?;
+                    listed
+                        .into_iter()
+                        .map(|project| ForgeRepo {
+                            full_name: project.path_with_namespace,
+                            ssh_url: project.ssh_url_to_repo,
+                            https_url: project.http_url_to_repo,
+                            is_archived: project.archived,
+                        })
+                        .collect()
+                }
+            };
+            let is_last_page = batch.len() < PER_PAGE;
+            repos.extend(batch);
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(repos)
+    }
+
+    /// `full_name` is `owner/name`, or the full namespace path on GitLab.
+    pub fn repo_state(&self, full_name: &str) -> io::Result<RemoteState> {
+        let url = match self.kind {
+            ForgeKind::Github => format!("{}/repos/{}", self.api_url, full_name),
+            ForgeKind::Gitlab => {
+                format!("{}/projects/{}", self.api_url, encode(full_name))
+            }
+        };
+        match self.get(&url) {
+            Ok(body) => {
+                let repo: Archived = serde_json::from_str(&body)?;
+                Ok(if repo.archived {
+                    RemoteState::Archived
+                } else {
+                    RemoteState::Active
+                })
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(RemoteState::Deleted),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The first open pull or merge request from `branch` of the repository.
+    pub fn open_request(&self, full_name: &str, branch: &str) -> io::Result<Option<OpenRequest>> {
+        match self.kind {
+            ForgeKind::Github => {
+                let owner = full_name.split('/').next().unwrap_or(full_name);
+                let url = format!(
+                    "{}/repos/{}/pulls?state=open&head={}",
+                    self.api_url,
+                    full_name,
+                    encode(&format!("{}:{}", owner, branch))
+                );
+                let pulls: Vec<GithubPull> = serde_json::from_str(&self.get(&url)?)?;
+                Ok(pulls.into_iter().next().map(|pull| OpenRequest {
+                    number: pull.number,
+                    url: pull.html_url,
+                }))
+            }
+            ForgeKind::Gitlab => {
+                let url = format!(
+                    "{}/projects/{}/merge_requests?state=opened&source_branch={}",
+                    self.api_url,
+                    encode(full_name),
+                    encode(branch)
+                );
+                let requests: Vec<GitlabMergeRequest> = serde_json::from_str(&self.get(&url)?)?;
+                Ok(requests.into_iter().next().map(|request| OpenRequest {
+                    number: request.iid,
+                    url: request.web_url,
+                }))
+            }
+        }
+    }
+
+    /// Fails with `NotFound` when the forge answers 404.
+    pub fn get(&self, url: &str) -> io::Result<String> {
+        let request = self.agent.get(url).header("User-Agent", "gittracker-rs");
+        let request = match self.kind {
+            ForgeKind::Github => request
+                .header("Authorization", &format!("Bearer {}", self.token))
+                .header("Accept", "application/vnd.github+json"),
+            ForgeKind::Gitlab => request.header("PRIVATE-TOKEN", &self.token),
+        };
+        let mut response = request.call().map_err(|err| match err {
+            ureq::Error::StatusCode(404) => {
+                io::Error::new(io::ErrorKind::NotFound, format!("GET {} returned 404", url))
+            }
+            err => io::Error::other(format!("GET {} failed: {}", url, err)),
+        })?;
+        response
+            .body_mut()
+            .read_to_string()
+            .map_err(|err| io::Error::other(format!("GET {} failed: {}", url, err)))
+    }
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters, so a path like
+/// `group/name` or a branch like `feature/x` fits in one URL component.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
@@ -0,0 +1,22 @@
+use std::fs;
+use std::io;
+
+use clap::CommandFactory;
+
+use crate::{Cli, CompletionsArgs, ManpagesArgs};
+
+/// Prints the completion script for the shell, for the binary's own name.
+pub fn completions(args: &CompletionsArgs) -> io::Result<()> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(args.shell, &mut command, name, &mut io::stdout());
+    Ok(())
+}
+
+/// Writes one page for the tool and one per subcommand, e.g. `gittracker-rs-serve.1`.
+pub fn manpages(args: &ManpagesArgs) -> io::Result<()> {
+    fs::create_dir_all(&args.dir)?;
+    clap_mangen::generate_to(Cli::command(), &args.dir)?;
+    println!("wrote man pages to {}", args.dir.display());
+    Ok(())
+}
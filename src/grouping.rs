@@ -0,0 +1,83 @@
+use std::cmp::Reverse;
+
+use clap::ValueEnum;
+
+use crate::RepoStatus;
+
+/// Heading for repos without a remote on a hosting server.
+const NO_REMOTE: &str = "(no remote)";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SortKey {
+    /// Path, alphabetically
+    Path,
+    /// Most uncommitted files first
+    Uncommitted,
+    /// Most unpushed commits first
+    Unpushed,
+    /// Oldest last commit first
+    LastCommit,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum GroupBy {
+    /// Host of `origin` or the first remote, e.g. `github.com`
+    RemoteHost,
+    /// Directory containing the repository
+    ParentDir,
+    /// Host and owner of `origin` or the first remote, e.g. `github.com/mycompany`
+    Org,
+}
+
+/// Stable, so ties keep the scan order.
+pub fn sort(statuses: &mut [RepoStatus], key: SortKey) {
+    match key {
+        SortKey::Path => statuses.sort_by(|a, b| a.path.cmp(&b.path)),
+        SortKey::Uncommitted => statuses.sort_by_key(|status| Reverse(status.uncommitted_changes)),
+        SortKey::Unpushed => statuses.sort_by_key(|status| Reverse(status.unpushed_commits)),
+        SortKey::LastCommit => statuses
+            .sort_by_key(|status| status.last_commit.as_ref().map(|commit| commit.timestamp)),
+    }
+}
+
+/// Moves repos of the same group next to each other, keeping their order within the
+/// group, and returns each repo's group heading.
+pub fn group(statuses: &mut Vec<RepoStatus>, by: GroupBy) -> Vec<String> {
+    let mut keyed: Vec<(String, RepoStatus)> = statuses
+        .drain(..)
+        .map(|status| (group_key(&status, by), status))
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut keys = Vec::with_capacity(keyed.len());
+    for (key, status) in keyed {
+        keys.push(key);
+        statuses.push(status);
+    }
+    keys
+}
+
+fn group_key(status: &RepoStatus, by: GroupBy) -> String {
+    if let GroupBy::ParentDir = by {
+        return status
+            .path
+            .parent()
+            .map(|parent| parent.display().to_string())
+            .unwrap_or_default();
+    }
+
+    let Some(remote) = &status.remote else {
+        return NO_REMOTE.to_string();
+    };
+    let Some(host) = &remote.host else {
+        return NO_REMOTE.to_string();
+    };
+    let key = match (by, &remote.full_name) {
+        (GroupBy::Org, Some(full_name)) => {
+            let owner = full_name.split('/').next().unwrap_or(full_name);
+            format!("{}/{}", host, owner)
+        }
+        _ => host.clone(),
+    };
+    key.to_lowercase()
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::git::remote_location_of;
+use crate::vcs::{self, Vcs, VcsKind};
+use crate::{LastCommit, RepoStatus, ScanError, ScanOptions, UntrackedFiles};
+
+/// Mercurial repos. Pushed commits turn public, so draft and secret ones are the
+/// unpushed ones; that needs no network, unlike `hg outgoing`.
+pub struct Mercurial;
+
+impl Vcs for Mercurial {
+    fn marker(&self) -> &'static str {
+        ".hg"
+    }
+
+    fn status(&self, repo_root: &Path, options: &ScanOptions) -> Result<RepoStatus, ScanError> {
+        let run = |args: &[&str]| hg(repo_root, options, args);
+
+        // Mercurial always lists untracked files one by one, so only `no` changes anything.
+        let changes = if options.untracked == Some(UntrackedFiles::No) {
+            run(&["status", "--modified", "--added", "--removed", "--deleted"])?
+        } else {
+            run(&["status"])?
+        };
+        let uncommitted_changes = changes.lines().filter(|line| !line.is_empty()).count();
+        let untracked_files = changes
+            .lines()
+            .filter(|line| line.starts_with("? "))
+            .count();
+        // Lists files of an interrupted merge; `U` marks the unresolved ones.
+        let conflicts = run(&["resolve", "--list"])
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| line.starts_with("U "))
+            .count();
+
+        let paths = run(&["paths"]).unwrap_or_default();
+        let remotes: Vec<(&str, &str)> = paths
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .collect();
+        let has_upstream = !remotes.is_empty();
+        let unpushed_commits = if has_upstream {
+            run(&["log", "-r", "not public()", "-T", "x\\n"])?
+                .lines()
+                .count()
+        } else {
+            0
+        };
+        let remote = remotes
+            .iter()
+            .find(|(name, _)| *name == "default")
+            .or(remotes.first())
+            .map(|(_, url)| remote_location_of(url));
+
+        let head = run(&[
+            "log",
+            "-r",
+            ".",
+            "-T",
+            "{rev}\\t{branch}\\t{date|hgdate}\\t{author|person}\\t{desc|firstline}\\n",
+        ])?;
+        let mut fields = head.trim_end().splitn(5, '\t');
+        let rev = fields.next().unwrap_or_default();
+        let branch = fields.next().map(str::to_string);
+        // The null revision stands in for the parent of a repo without commits.
+        let last_commit = if rev == "-1" {
+            None
+        } else {
+            parse_last_commit(fields)
+        };
+
+        Ok(RepoStatus {
+            is_dirty: uncommitted_changes > 0 || unpushed_commits > 0,
+            uncommitted_changes,
+            conflicts,
+            untracked_files,
+            files: vcs::changed_files(&changes, options),
+            unpushed_commits,
+            has_upstream,
+            branch,
+            last_touched: last_commit.as_ref().map(|commit| commit.timestamp),
+            last_commit,
+            remotes: remotes.iter().map(|(name, _)| name.to_string()).collect(),
+            remote,
+            ..vcs::empty_status(repo_root, VcsKind::Hg)
+        })
+    }
+}
+
+fn hg(repo_root: &Path, options: &ScanOptions, args: &[&str]) -> Result<String, ScanError> {
+    let mut command = Command::new("hg");
+    // Output free of user aliases, localization and color.
+    command.env("HGPLAIN", "1").args(args);
+    vcs::run(command, repo_root, options)
+}
+
+/// The date field is `hgdate`: seconds since the epoch, then the timezone offset.
+fn parse_last_commit<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<LastCommit> {
+    Some(LastCommit {
+        timestamp: fields.next()?.split(' ').next()?.parse().ok()?,
+        author: fields.next()?.to_string(),
+        subject: fields.next().unwrap_or_default().to_string(),
+    })
+}
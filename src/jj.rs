@@ -0,0 +1,140 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::git::remote_location_of;
+use crate::vcs::{self, Vcs, VcsKind};
+use crate::{LastCommit, RepoStatus, ScanError, ScanOptions};
+
+/// Bookmarks on real remotes; colocated repos also mirror git's refs as `@git`.
+const REMOTE_BOOKMARKS: &str = r#"remote_bookmarks() ~ remote_bookmarks(remote=exact:"git")"#;
+
+/// Jujutsu repos, whose working copy is itself a commit (`@`): its changes count as
+/// uncommitted, and its ancestors missing from every remote bookmark as unpushed.
+pub struct Jujutsu;
+
+impl Vcs for Jujutsu {
+    fn marker(&self) -> &'static str {
+        ".jj"
+    }
+
+    fn status(&self, repo_root: &Path, options: &ScanOptions) -> Result<RepoStatus, ScanError> {
+        // The first command snapshots the working copy; the others reuse that snapshot.
+        let changes = jj(repo_root, options, false, &["diff", "--summary", "-r", "@"])?;
+        let run = |args: &[&str]| jj(repo_root, options, true, args);
+
+        let working_copy = run(&[
+            "log",
+            "-r",
+            "@",
+            "-T",
+            r#"conflict ++ "\t" ++ empty ++ "\t" ++ committer.timestamp().format("%s") ++ "\n""#,
+        ])?;
+        let mut fields = working_copy.trim_end().split('\t');
+        let has_conflicts = fields.next() == Some("true");
+        let is_empty = fields.next() == Some("true");
+        let touched_at = fields.next().and_then(|time| time.parse::<u64>().ok());
+        let conflicts = if has_conflicts {
+            run(&["resolve", "--list", "-r", "@"])?.lines().count()
+        } else {
+            0
+        };
+
+        let has_upstream = !run(&[
+            "log",
+            "-r",
+            REMOTE_BOOKMARKS,
+            "--limit",
+            "1",
+            "-T",
+            r#""x""#,
+        ])?
+        .trim()
+        .is_empty();
+        let unpushed_commits = if has_upstream {
+            let range = format!("({})..@- ~ root()", REMOTE_BOOKMARKS);
+            run(&["log", "-r", &range, "-T", r#""x\n""#])?
+                .lines()
+                .count()
+        } else {
+            0
+        };
+
+        let last_commit = run(&[
+            "log",
+            "-r",
+            "latest(@- ~ root())",
+            "-T",
+            r#"committer.timestamp().format("%s") ++ "\t" ++ author.name() ++ "\t" ++ description.first_line() ++ "\n""#,
+        ])
+        .ok()
+        .and_then(|log| parse_last_commit(&log));
+        let branch = run(&[
+            "log",
+            "-r",
+            "latest((@ | @-) & bookmarks())",
+            "-T",
+            r#"local_bookmarks.map(|b| b.name()).join("\n") ++ "\n""#,
+        ])
+        .ok()
+        .and_then(|names| names.lines().next().map(str::to_string))
+        .filter(|name| !name.is_empty());
+
+        let remotes_output = run(&["git", "remote", "list"]).unwrap_or_default();
+        let remotes: Vec<(&str, &str)> = remotes_output
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .collect();
+        let remote = remotes
+            .iter()
+            .find(|(name, _)| *name == "origin")
+            .or(remotes.first())
+            .map(|(_, url)| remote_location_of(url));
+
+        let uncommitted_changes = changes.lines().filter(|line| !line.is_empty()).count();
+        let last_commit_time = last_commit.as_ref().map(|commit| commit.timestamp);
+        Ok(RepoStatus {
+            is_dirty: uncommitted_changes > 0 || unpushed_commits > 0 || conflicts > 0,
+            uncommitted_changes,
+            conflicts,
+            files: vcs::changed_files(&changes, options),
+            unpushed_commits,
+            has_upstream,
+            branch,
+            // `@` is rewritten on every snapshot that finds a change.
+            last_touched: last_commit_time.max(touched_at.filter(|_| !is_empty)),
+            last_commit,
+            remotes: remotes.iter().map(|(name, _)| name.to_string()).collect(),
+            remote,
+            ..vcs::empty_status(repo_root, VcsKind::Jj)
+        })
+    }
+}
+
+fn jj(
+    repo_root: &Path,
+    options: &ScanOptions,
+    should_reuse_snapshot: bool,
+    args: &[&str],
+) -> Result<String, ScanError> {
+    let mut command = Command::new("jj");
+    command.args(["--no-pager", "--color=never"]);
+    if should_reuse_snapshot {
+        command.arg("--ignore-working-copy");
+    }
+    // Every `log` here wants plain lines, not the graph.
+    if args.first() == Some(&"log") {
+        command.arg("log").arg("--no-graph").args(&args[1..]);
+    } else {
+        command.args(args);
+    }
+    vcs::run(command, repo_root, options)
+}
+
+fn parse_last_commit(log: &str) -> Option<LastCommit> {
+    let mut fields = log.trim_end().splitn(3, '\t');
+    Some(LastCommit {
+        timestamp: fields.next()?.parse().ok()?,
+        author: fields.next()?.to_string(),
+        subject: fields.next().unwrap_or_default().to_string(),
+    })
+}
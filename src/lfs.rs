@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use crate::ScanOptions;
+use crate::git::{run_git, run_git_with_timeout};
+
+/// LFS state of a repo whose `.gitattributes` routes files through the LFS filter.
+#[derive(Clone, Debug, Serialize)]
+pub struct LfsStatus {
+    /// `git lfs` is available on this machine.
+    #[serde(rename = "lfs_installed")]
+    pub is_installed: bool,
+    /// The repo's pre-push hook uploads LFS objects; without it, pushes leave them behind.
+    #[serde(rename = "lfs_initialized")]
+    pub is_initialized: bool,
+    /// LFS objects committed but not yet on the LFS server; unknown without `git lfs`.
+    #[serde(
+        rename = "lfs_pending_uploads",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pending_uploads: Option<usize>,
+}
+
+impl LfsStatus {
+    /// Whether pushed commits may still reference objects the server doesn't have.
+    pub fn needs_attention(&self) -> bool {
+        !self.is_installed || !self.is_initialized || self.pending_uploads.is_some_and(|n| n > 0)
+    }
+}
+
+/// `None` for repos that don't use LFS.
+pub fn status(repo_root: &Path, options: &ScanOptions) -> Option<LfsStatus> {
+    if !uses_lfs(repo_root) {
+        return None;
+    }
+    if !is_installed() {
+        return Some(LfsStatus {
+            is_installed: false,
+            is_initialized: false,
+            pending_uploads: None,
+        });
+    }
+
+    let pending_uploads = run_git_with_timeout(repo_root, &["lfs", "status"], options.timeout)
+        .ok()
+        .map(|output| count_pending_uploads(&output));
+    Some(LfsStatus {
+        is_installed: true,
+        is_initialized: has_pre_push_hook(repo_root),
+        pending_uploads,
+    })
+}
+
+fn uses_lfs(repo_root: &Path) -> bool {
+    fs::read_to_string(repo_root.join(".gitattributes"))
+        .is_ok_and(|attributes| attributes.lines().any(|line| line.contains("filter=lfs")))
+}
+
+fn is_installed() -> bool {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    *INSTALLED.get_or_init(|| {
+        Command::new("git")
+            .args(["lfs", "version"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    })
+}
+
+fn has_pre_push_hook(repo_root: &Path) -> bool {
+    // `--git-path` resolves `core.hooksPath` and linked worktrees.
+    let Ok(hook) = run_git(repo_root, &["rev-parse", "--git-path", "hooks/pre-push"]) else {
+        return false;
+    };
+    fs::read_to_string(repo_root.join(hook.trim()))
+        .is_ok_and(|script| script.contains("git lfs") || script.contains("git-lfs"))
+}
+
+/// Counts the entries under `git lfs status`'s "Objects to be pushed to <ref>:" heading.
+fn count_pending_uploads(output: &str) -> usize {
+    let mut is_push_section = false;
+    let mut pending = 0;
+    for line in output.lines() {
+        if line.starts_with("Objects to be pushed to") {
+            is_push_section = true;
+        } else if line.starts_with("Objects ") {
+            is_push_section = false;
+        } else if is_push_section && !line.trim().is_empty() {
+            pending += 1;
+        }
+    }
+    pending
+}
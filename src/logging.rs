@@ -0,0 +1,37 @@
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+use tracing_subscriber::EnvFilter;
+
+/// Sets up diagnostics from `--log-level`, else `RUST_LOG`. Without either, nothing is
+/// logged to stderr, and a `--log-file` gets `info` and above.
+pub fn init(level: Option<&str>, file: Option<&Path>) -> io::Result<()> {
+    let default = if file.is_some() { "info" } else { "off" };
+    let filter = match level {
+        Some(level) => EnvFilter::try_new(level).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid --log-level `{}`: {}", level, err),
+            )
+        })?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default)),
+    };
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = match file {
+        // One JSON object per line, for `jq` or a log shipper.
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            builder
+                .json()
+                .with_writer(std::sync::Mutex::new(file))
+                .try_init()
+        }
+        None => builder
+            .with_ansi(io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none())
+            .with_writer(io::stderr)
+            .try_init(),
+    };
+    result.map_err(io::Error::other)
+}
@@ -1,9 +1,85 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use serde::Serialize;
-use std::ffi::OsStr;
-use std::path::{Path, PathBuf};
-use std::process::Command;
-use walkdir::{DirEntry, WalkDir};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+mod archive;
+mod baseline;
+mod bootstrap;
+mod check;
+mod checks;
+mod cloud;
+mod color;
+mod config;
+mod daemon;
+mod deps;
+mod diff;
+mod enrich;
+mod events;
+mod forge;
+mod generate;
+mod git;
+mod grouping;
+mod hg;
+mod jj;
+mod lfs;
+mod logging;
+mod maintain;
+mod manifest;
+mod migrate;
+mod notify;
+mod orphans;
+mod overrides;
+mod policy;
+mod pool;
+mod progress;
+mod redact;
+mod runner;
+mod sample;
+mod scan;
+mod score;
+mod serve;
+mod sink;
+mod sizes;
+mod trend;
+mod triage;
+mod vcs;
+mod verify;
+mod volume;
+mod watch;
+mod webhook;
+
+use color::{ColorChoice, Style, Theme};
+use config::Config;
+use deps::DependencyDrift;
+use enrich::Enrichment;
+use forge::RemoteState;
+use grouping::{GroupBy, SortKey};
+use lfs::LfsStatus;
+use policy::{PolicyEngine, PolicyViolation};
+use progress::{ProgressDisplay, ScanProgress};
+use redact::Redactor;
+use runner::RemoteTarget;
+use sample::{SampleInfo, SampleReport, SampleSize};
+use scan::{scan_remote, scan_root, scan_root_with_progress};
+use sink::{Report, SinkSpec};
+use sizes::RepoSizes;
+use trend::Trend;
+use vcs::VcsKind;
+use volume::Volume;
+
+/// Version of the `--json` report, described by `--print-schema`. Fields are only ever
+/// added; renaming, removing or retyping one bumps this.
+const SCHEMA_VERSION: u32 = 1;
+
+/// JSON Schema of the `--json` report; keep it in step with the serialized types.
+const REPORT_SCHEMA: &str = include_str!("report.schema.json");
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -11,10 +87,17 @@ use walkdir::{DirEntry, WalkDir};
     about = "Scan folders for git repos with local changes"
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Root folder to scan
     #[arg(default_value = ".")]
     root: PathBuf,
 
+    /// Print the JSON Schema of the `--json` report and exit
+    #[arg(long)]
+    print_schema: bool,
+
     /// Output JSON instead of human-readable lines
     #[arg(long)]
     json: bool,
@@ -22,180 +105,1722 @@ struct Cli {
     /// Include clean repositories in output
     #[arg(long)]
     show_clean: bool,
+
+    /// When to color the human report
+    #[arg(long, value_enum, default_value_t)]
+    color: ColorChoice,
+
+    /// Print nothing and report only through the exit code; files, webhooks and
+    /// other non-terminal sinks still receive the report
+    #[arg(long, short)]
+    quiet: bool,
+
+    /// Only print the totals and which repositories fall in each category; with
+    /// `--json`, just the totals object
+    #[arg(long)]
+    summary: bool,
+
+    /// Print only the paths of the repositories the report would list, for
+    /// piping into xargs or fzf
+    #[arg(long, conflicts_with_all = ["json", "summary", "sink"])]
+    paths_only: bool,
+
+    /// End each path with NUL instead of a newline (with --paths-only)
+    #[arg(short = '0', long = "null", requires = "paths_only")]
+    is_nul_terminated: bool,
+
+    /// Write the report to a file instead of stdout
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Encrypt the output files to an age recipient (requires `age` or `rage`)
+    #[arg(long, value_name = "RECIPIENT")]
+    encrypt_to: Vec<String>,
+
+    /// Send the report to this sink instead: `human`, `json`, `human:FILE`,
+    /// `json:FILE` or `webhook:URL` (repeatable; overrides `sinks` in the config)
+    #[arg(long, value_name = "SINK", conflicts_with_all = ["json", "output"])]
+    sink: Vec<SinkSpec>,
+
+    /// Only list the N worst dirty repositories, ranked by `--top-by`; totals still cover
+    /// all, and JSON adds them as `top` next to the full `repos`
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Metric used to rank repositories for `--top`
+    #[arg(long, value_enum, default_value_t = TopMetric::Score, requires = "top")]
+    top_by: TopMetric,
+
+    /// Also scan inside a running container, as `NAME[:ROOT]` (repeatable)
+    #[arg(long, value_name = "CONTAINER[:ROOT]", value_parser = runner::parse_docker_target)]
+    docker: Vec<RemoteTarget>,
+
+    /// Also scan a host over ssh, as `HOST[:ROOT]` (repeatable)
+    #[arg(long, value_name = "HOST[:ROOT]", value_parser = runner::parse_ssh_target)]
+    ssh: Vec<RemoteTarget>,
+
+    /// Inspect only a random sample of the discovered repos (e.g. `10%` or `50`)
+    /// and extrapolate the summary
+    #[arg(long, value_name = "SIZE", value_parser = sample::parse_sample_size)]
+    sample: Option<SampleSize>,
+
+    /// Seed for `--sample`, to repeat the same selection
+    #[arg(long, value_name = "SEED", requires = "sample")]
+    sample_seed: Option<u64>,
+
+    /// Exit with status 2 if any directory or repository could not be scanned
+    #[arg(long)]
+    strict: bool,
+
+    /// Only report repositories whose main remote is on this host (repeatable)
+    #[arg(long, value_name = "HOST")]
+    only_host: Vec<String>,
+
+    /// Only report repositories without any configured remote
+    #[arg(long)]
+    only_no_remote: bool,
+
+    /// Report working tree and .git sizes plus loose object and pack counts
+    #[arg(long)]
+    sizes: bool,
+
+    /// With --sizes, list the largest repositories first
+    #[arg(long, requires = "sizes", conflicts_with = "sort")]
+    sort_by_size: bool,
+
+    /// Order repositories by this key
+    #[arg(long, value_enum)]
+    sort: Option<SortKey>,
+
+    /// Show repositories under a heading per group, sorted within each group
+    #[arg(long, value_enum)]
+    group_by: Option<GroupBy>,
+
+    /// Ask GitHub or GitLab whether each remote is archived or deleted and whether the
+    /// current branch has an open pull request (token from GITHUB_TOKEN or GITLAB_TOKEN)
+    #[arg(long)]
+    enrich: bool,
+
+    /// Report the file system of each repository and whether its volume is encrypted
+    #[arg(long)]
+    audit_encryption: bool,
+
+    /// Flag git dependencies in lock files that pin an older commit of another scanned repo
+    #[arg(long)]
+    check_deps: bool,
+
+    /// Exit 3 instead of 1 when any repository has unresolved merge conflicts
+    #[arg(long)]
+    fail_on_conflicts: bool,
+
+    /// Exit 1 only if the scan is worse than this saved `--json` report: new dirty
+    /// repositories or more uncommitted files or unpushed commits than before
+    #[arg(long, value_name = "BASELINE")]
+    assert_no_regression: Option<PathBuf>,
+
+    /// Only report dirty repositories left untouched for more than this many days
+    #[arg(long, value_name = "N")]
+    stale_days: Option<u64>,
+
+    /// Neither record this scan's totals nor show how they changed since earlier
+    /// scans; trends are only kept for full local scans without filters
+    #[arg(long)]
+    no_trend: bool,
+
+    /// Skip the executables in `checks.d` next to the config file
+    #[arg(long)]
+    no_checks: bool,
+
+    /// Give up on a repository when git takes longer than this (e.g. `10s`)
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Only find repositories at most N directories below the root
+    #[arg(long, global = true, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Stay on the filesystem of the root (skip NFS and other mounts)
+    #[arg(long, global = true)]
+    one_file_system: bool,
+
+    /// Follow symbolic links to directories; loops are skipped and a repository
+    /// reached through several links is reported once
+    #[arg(long, global = true)]
+    follow_symlinks: bool,
+
+    /// Version control systems to look for, e.g. `git,jj,hg`; colocated jj repos are
+    /// reported as jj when both are enabled
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "git"
+    )]
+    vcs: Vec<VcsKind>,
+
+    /// How to treat repositories nested inside other repositories
+    #[arg(long, global = true, value_enum, default_value_t = NestedPolicy::Include)]
+    nested: NestedPolicy,
+
+    /// Run `git fetch --prune` in every repository before inspecting it
+    #[arg(long, global = true)]
+    fetch: bool,
+
+    /// With --fetch, skip repositories fetched more recently than this (e.g. 1h)
+    #[arg(long, global = true, requires = "fetch", value_parser = parse_duration)]
+    fetch_max_age: Option<Duration>,
+
+    /// Which remotes unpushed commits are counted against
+    #[arg(long, global = true, value_enum, default_value_t = CheckRemotes::Upstream)]
+    check_remotes: CheckRemotes,
+
+    /// List each dirty repository's changed files with their `git status --short` codes
+    #[arg(short = 'v', long, visible_alias = "verbose", global = true)]
+    files: bool,
+
+    /// With --files, list at most this many files per repository
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        default_value_t = 20,
+        requires = "files"
+    )]
+    max_files: usize,
+
+    /// Which untracked files `git status` lists and counts as changes, like git's
+    /// `--untracked-files` (default: each repo's `status.showUntrackedFiles`)
+    #[arg(long, global = true, value_enum)]
+    untracked: Option<UntrackedFiles>,
+
+    /// Stop scanning after this long and list what was left unscanned (e.g. `60s`)
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_duration)]
+    max_duration: Option<Duration>,
+
+    /// Config file (default: `~/.config/gittracker/config.toml`)
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Log what the scan does to stderr: `error`, `warn`, `info`, `debug`, `trace`, or
+    /// `RUST_LOG` directives such as `gittracker_rs::git=debug` (default: `RUST_LOG`)
+    #[arg(long, global = true, value_name = "LEVEL")]
+    log_level: Option<String>,
+
+    /// Write the log to this file as JSON lines instead of to stderr
+    #[arg(long, global = true, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// Replace repo paths and other identifying values with stable salted hashes
+    #[arg(long, global = true)]
+    redact: bool,
+
+    /// Salt for `--redact` hashes; keep it constant to compare reports [default: a random
+    /// salt generated once and kept in the config directory]
+    #[arg(
+        long,
+        global = true,
+        env = "GITTRACKER_REDACT_SALT",
+        hide_env_values = true
+    )]
+    redact_salt: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Run periodic scans and expose the results over HTTP
+    Serve(ServeArgs),
+    /// Archive a repository and remove its working copy
+    Archive(ArchiveArgs),
+    /// Recreate every repository under a root at a new location
+    Migrate(MigrateArgs),
+    /// Check that branches which look pushed still exist on their remotes
+    Verify(VerifyArgs),
+    /// Re-check repositories as files change and print what changed
+    Watch(WatchArgs),
+    /// Check a single repository against the configured policies
+    Check(CheckArgs),
+    /// Run routine git housekeeping in every repository under a root
+    Maintain(MaintainArgs),
+    /// Create a repository from a configured template and register it in the manifest
+    New(NewArgs),
+    /// List repositories in a forge organization that have no local clone
+    Orphans(OrphansArgs),
+    /// Compare a scan with a saved `--json` report and list what changed
+    Diff(DiffArgs),
+    /// Rescan in the background and keep the latest results for `status --cached`
+    Daemon(DaemonArgs),
+    /// List dirty repositories, from a fresh scan or the daemon's latest one
+    Status(StatusArgs),
+    /// Go through dirty repositories one by one to commit, stash or push them
+    Triage(TriageArgs),
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+    /// Write man pages for the tool and every subcommand into a folder
+    Manpages(ManpagesArgs),
+}
+
+#[derive(Args, Debug)]
+struct CompletionsArgs {
+    /// Shell to complete in
+    #[arg(value_enum)]
+    shell: clap_complete::Shell,
+}
+
+#[derive(Args, Debug)]
+struct ManpagesArgs {
+    /// Folder to write the pages to, created if missing
+    dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct TriageArgs {
+    /// Root folder to scan
+    #[arg(default_value = ".")]
+    root: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct DaemonArgs {
+    /// Root folder to scan
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    /// Time between scans (e.g. `30s`, `5m`, `1h`); `0` scans only on file changes
+    #[arg(long, default_value = "5m", value_parser = parse_duration)]
+    interval: Duration,
+
+    /// Also rescan once files under the root settle after a change; `[watch]` in the
+    /// config file sets the exclusions, debounce and cooldown
+    #[arg(long)]
+    on_change: bool,
+
+    /// Where to keep the latest results [default: daemon-state.json next to the config file]
+    #[arg(long, value_name = "FILE")]
+    state: Option<PathBuf>,
+
+    /// Show desktop notifications when repos become dirty or gain unpushed commits
+    #[arg(long)]
+    notify: bool,
+
+    /// Also notify when commits stay unpushed longer than this (e.g. `1d`)
+    #[arg(long, value_name = "DURATION", requires = "notify", value_parser = parse_duration)]
+    notify_stale_after: Option<Duration>,
+}
+
+#[derive(Args, Debug)]
+struct StatusArgs {
+    /// Root folder to scan
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    /// Print the daemon's latest results instead of scanning
+    #[arg(long)]
+    cached: bool,
+
+    /// State file the daemon writes, if it was given `--state`
+    #[arg(long, value_name = "FILE", requires = "cached")]
+    state: Option<PathBuf>,
+
+    /// Include clean repositories
+    #[arg(long)]
+    show_clean: bool,
+
+    /// Print the whole `--json` report, clean repositories included
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Root folder to scan
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    /// Saved `--json` report to compare against
+    #[arg(long, value_name = "FILE")]
+    baseline: PathBuf,
+
+    /// Print the changes as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct OrphansArgs {
+    /// Organization (GitHub) or group path (GitLab) to list
+    #[arg(long)]
+    org: String,
+
+    /// Forge hosting the organization
+    #[arg(long, value_enum, default_value_t)]
+    forge: forge::ForgeKind,
+
+    /// API base URL for self-hosted forges, e.g. https://gitlab.example.com/api/v4
+    #[arg(long)]
+    api_url: Option<String>,
+
+    /// Access token allowed to list the organization's repositories
+    #[arg(long, env = "GITTRACKER_FORGE_TOKEN", hide_env_values = true)]
+    token: String,
+
+    /// Roots to look for existing clones in
+    #[arg(default_value = ".")]
+    roots: Vec<PathBuf>,
+
+    /// Also list archived repositories
+    #[arg(long)]
+    include_archived: bool,
+
+    /// Clone the missing repositories into this folder
+    #[arg(long, value_name = "DIR")]
+    clone_missing: Option<PathBuf>,
+
+    /// Clone over HTTPS instead of SSH
+    #[arg(long)]
+    https: bool,
+}
+
+#[derive(Args, Debug)]
+struct NewArgs {
+    /// Folder to create the repository in
+    path: PathBuf,
+
+    /// Template name from `[templates.<name>]` in the config file
+    #[arg(long)]
+    template: String,
+}
+
+#[derive(Args, Debug)]
+struct MaintainArgs {
+    /// Root folder to scan
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    /// Also run `git maintenance run` after `gc --auto` and `remote prune origin`
+    #[arg(long)]
+    maintenance: bool,
+
+    /// Repositories to maintain at once (default: one per CPU)
+    #[arg(long, short = 'j')]
+    jobs: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+struct CheckArgs {
+    /// Repository to check, or any folder inside it
+    #[arg(long, default_value = ".")]
+    repo: PathBuf,
+
+    /// Print only policy violations, for use as a pre-commit framework hook
+    #[arg(long)]
+    pre_commit: bool,
+}
+
+#[derive(Args, Debug)]
+struct WatchArgs {
+    /// Root folder to watch
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    /// How changes are printed
+    #[arg(long, value_enum, default_value_t)]
+    format: WatchFormat,
+
+    /// Show desktop notifications when repos become dirty or gain unpushed commits
+    #[arg(long)]
+    notify: bool,
+
+    /// Also notify when commits stay unpushed longer than this (e.g. `1d`)
+    #[arg(long, value_name = "DURATION", requires = "notify", value_parser = parse_duration)]
+    notify_stale_after: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum WatchFormat {
+    /// One line per changed repository
+    #[default]
+    Text,
+    /// One RFC 6902 JSON patch per line, applied to `{"repos": {}}` keyed by path
+    Jsonl,
+}
+
+#[derive(Args, Debug)]
+struct VerifyArgs {
+    /// Root folder to scan
+    #[arg(default_value = ".")]
+    root: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct MigrateArgs {
+    /// Root folder whose repositories are migrated
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    /// Destination folder; relative layout under the root is preserved
+    #[arg(long)]
+    to: PathBuf,
+
+    /// Report what would happen without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+struct ArchiveArgs {
+    /// Repository to archive
+    repo: PathBuf,
+
+    /// Folder receiving bundles and the archive log
+    #[arg(long)]
+    dest: PathBuf,
+
+    /// Report what would happen without writing or removing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Archive even with untracked or ignored files, which are removed with the repo
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// Root folder to scan
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    /// Address for the JSON API (`/repos`, `/repos/{id}`, `/rescan`); a bare `:PORT` is
+    /// localhost only
+    #[arg(long, value_name = "ADDR", required_unless_present = "metrics")]
+    listen: Option<String>,
+
+    /// Address for the Prometheus metrics endpoint (e.g. `:9184` for localhost)
+    #[arg(long, value_name = "ADDR")]
+    metrics: Option<String>,
+
+    /// Time between scans (e.g. `30s`, `5m`, `1h`); `0` scans only on demand
+    #[arg(long, default_value = "5m", value_parser = parse_duration)]
+    interval: Duration,
+
+    /// Show desktop notifications when repos become dirty or gain unpushed commits
+    #[arg(long)]
+    notify: bool,
+
+    /// Also notify when commits stay unpushed longer than this (e.g. `1d`)
+    #[arg(long, value_name = "DURATION", requires = "notify", value_parser = parse_duration)]
+    notify_stale_after: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Serialize)]
 struct RepoStatus {
     path: PathBuf,
+    /// Version control system managing the repo.
+    vcs: VcsKind,
     is_dirty: bool,
+    /// A bare repository, which has no work tree and so no uncommitted changes.
+    is_bare: bool,
     uncommitted_changes: usize,
+    /// Unmerged paths from a conflicted merge, rebase or cherry-pick; also counted in
+    /// `uncommitted_changes`.
+    conflicts: usize,
+    /// Untracked files, also counted in `uncommitted_changes`.
+    #[serde(skip)]
+    untracked_files: usize,
+    /// The first `--max-files` changed files, with `--files`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    files: Vec<ChangedFile>,
     unpushed_commits: usize,
+    /// Upstream commits not yet merged; only as fresh as the last fetch (see `--fetch`).
+    behind_commits: usize,
+    /// Entries in `git stash list`; always 0 for Jujutsu and Mercurial.
+    stashes: usize,
+    /// Hygiene penalty weighted per `[score]` in the config; 0 for a spotless repo.
+    score: f64,
     has_upstream: bool,
+    /// Current branch; absent for detached HEADs and bare repositories.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_commit: Option<LastCommit>,
+    /// Latest commit or uncommitted file change, in seconds since the Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_touched: Option<u64>,
+    /// Configured remote names; empty when the repo is not backed up anywhere.
+    remotes: Vec<String>,
+    /// Where `origin`, or else the first remote, points.
+    #[serde(flatten)]
+    remote: Option<RemoteLocation>,
+    /// Remote repository state and open pull request, with `--enrich`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enrichment: Option<Enrichment>,
+    /// Commits each remote is missing, with `--check-remotes all`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    remote_ahead: Vec<RemoteAhead>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    policy_violations: Vec<PolicyViolation>,
+    /// Disk usage and object store counts, with `--sizes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sizes: Option<RepoSizes>,
+    /// Git dependencies pinned behind another scanned repo, with `--check-deps`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dependency_drift: Vec<DependencyDrift>,
+    /// Present for repos that store files in Git LFS.
+    #[serde(flatten)]
+    lfs: Option<LfsStatus>,
+    /// File system and encryption state, with `--audit-encryption`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume: Option<Volume>,
+    /// Cloud sync service whose folder holds the repo, e.g. `Dropbox`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync_service: Option<&'static str>,
+    /// Fields from the repo's manifest entry, passed through unchanged.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, serde_json::Value>,
+    /// What each check in `checks.d` reported, by check name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    findings: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+    /// Inspected through `git status --porcelain` v1 because the git that ran it predates
+    /// 2.11; ahead and behind counts then come from `rev-list`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    porcelain_v1: bool,
+    /// Enclosing repository, for repos nested inside another one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<PathBuf>,
+}
+
+/// One line of `git status --short`.
+#[derive(Clone, Debug, Serialize)]
+struct ChangedFile {
+    /// Index and work tree state, e.g. ` M`, `A `, `UU` or `??`.
+    code: String,
+    /// The new path, for renames.
+    path: PathBuf,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct LastCommit {
+    /// Committer date, in seconds since the Unix epoch.
+    timestamp: u64,
+    author: String,
+    subject: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct RemoteLocation {
+    remote_url: String,
+    /// Hosting server, e.g. `github.com`; absent for remotes on the local file system.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    /// Repository path on the host, `owner/name` on GitHub and `group/.../name` on GitLab.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    full_name: Option<String>,
+}
+
+/// How far the local branch is ahead of its counterpart on one remote.
+#[derive(Clone, Debug, Serialize)]
+struct RemoteAhead {
+    remote: String,
+    /// Commits on the local branch that the remote does not have.
+    ahead: usize,
+    /// False when the remote has no branch of the same name; `ahead` then counts
+    /// commits on none of the remote's branches.
+    has_branch: bool,
+}
+
+/// A directory or repository that could not be scanned.
+#[derive(Debug, Serialize)]
+struct ScanError {
+    path: PathBuf,
+    kind: ScanErrorKind,
+    message: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ScanErrorKind {
+    /// The directory walk could not read an entry
+    Walk,
+    /// Git (or jj or hg) could not be run or exited with an error
+    Git,
+    /// Git exceeded `--timeout`
+    Timeout,
+    /// `--fetch` failed; the repo was still inspected with its last fetched state
+    Fetch,
+}
+
+#[derive(Debug, Default)]
+struct ScanResult {
+    statuses: Vec<RepoStatus>,
+    errors: Vec<ScanError>,
+    /// Paths skipped because `--max-duration` ran out.
+    unscanned: Vec<PathBuf>,
+    sample: Option<SampleInfo>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum NestedPolicy {
+    /// Report nested repositories alongside their parents
+    #[default]
+    Include,
+    /// Do not descend into repositories once found
+    Skip,
+    /// Report only repositories nested inside another repository
+    ChildrenOnly,
+}
+
+impl ScanResult {
+    fn merge(&mut self, other: ScanResult) {
+        self.statuses.extend(other.statuses);
+        self.errors.extend(other.errors);
+        self.unscanned.extend(other.unscanned);
+    }
+}
+
+/// Settings that affect how repositories are discovered and inspected.
+#[derive(Clone, Debug, Default)]
+struct ScanOptions {
+    timeout: Option<Duration>,
+    max_depth: Option<usize>,
+    is_one_file_system: bool,
+    should_follow_symlinks: bool,
+    /// Systems whose repositories are reported; empty means git only.
+    vcs: Vec<VcsKind>,
+    nested: NestedPolicy,
+    max_duration: Option<Duration>,
+    sample: Option<SampleSize>,
+    sample_seed: Option<u64>,
+    check_remotes: CheckRemotes,
+    /// `None` leaves it to each repo's git config.
+    untracked: Option<UntrackedFiles>,
+    /// How many changed files to list per repo, with `--files`.
+    max_files: Option<usize>,
+    should_fetch: bool,
+    fetch_max_age: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum CheckRemotes {
+    /// Only the current branch's upstream
+    #[default]
+    Upstream,
+    /// Also the same branch on every configured remote
+    All,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum UntrackedFiles {
+    /// Ignore untracked files; the fastest on repos full of build output
+    No,
+    /// Untracked folders count once, not per file inside them
+    Normal,
+    /// Every untracked file counts
+    All,
+}
+
+impl UntrackedFiles {
+    fn as_str(self) -> &'static str {
+        match self {
+            UntrackedFiles::No => "no",
+            UntrackedFiles::Normal => "normal",
+            UntrackedFiles::All => "all",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+enum TopMetric {
+    /// Weighted hygiene score, see `[score]` in the config
+    Score,
+    /// Commits ahead of upstream
+    Unpushed,
+    /// Uncommitted files
+    Uncommitted,
+    /// Days since a dirty repo was last touched
+    Age,
+}
+
+#[derive(Debug, Serialize)]
+struct TopList<'a> {
+    metric: TopMetric,
+    repos: Vec<&'a RepoStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    total: usize,
+    dirty: usize,
+    clean: usize,
+    repos_with_uncommitted: usize,
+    repos_with_unpushed: usize,
+    repos_with_conflicts: usize,
+    repos_without_remote: usize,
+    policy_violations: usize,
+    /// Only counted with `--audit-encryption`.
+    repos_on_unencrypted_volumes: usize,
 }
 
 #[derive(Debug, Serialize)]
 struct JsonOutput<'a> {
+    schema_version: u32,
     total: usize,
     repos: &'a [RepoStatus],
+    errors: &'a [ScanError],
+    unscanned: &'a [PathBuf],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample: Option<&'a SampleReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top: Option<&'a TopList<'a>>,
+}
+
+/// The `--json --summary` document.
+#[derive(Debug, Serialize)]
+struct SummaryOutput<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    summary: &'a Summary,
 }
 
 fn main() {
     let cli = Cli::parse();
-    let statuses = scan_root(&cli.root);
-
-    if cli.json {
-        print_json(&statuses);
-    } else {
-        print_human(&statuses, cli.show_clean);
+    if cli.print_schema {
+        print!("{}", REPORT_SCHEMA);
+        return;
     }
-
-    let dirty_count = statuses.iter().filter(|status| status.is_dirty).count();
-    let clean_count = statuses.len().saturating_sub(dirty_count);
-    let uncommitted_count = statuses
-        .iter()
-        .filter(|status| status.uncommitted_changes > 0)
-        .count();
-    let unpushed_count = statuses
-        .iter()
-        .filter(|status| status.unpushed_commits > 0)
-        .count();
-    let has_dirty = dirty_count > 0;
-    if !cli.json && !cli.show_clean && !has_dirty {
-        println!("no repositories with local changes found");
-    }
-
-    if !cli.json {
-        println!("scanned {} repositories", statuses.len());
-        println!("dirty: {}, clean: {}", dirty_count, clean_count);
-        println!(
-            "repos with uncommitted changes: {}, unpushed commits: {}",
-            uncommitted_count, unpushed_count
-        );
+    if let Err(err) = logging::init(cli.log_level.as_deref(), cli.log_file.as_deref()) {
+        eprintln!("error: failed to set up logging: {}", err);
+        std::process::exit(2);
     }
+    let config = match config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: failed to load config: {}", err);
+            std::process::exit(2);
+        }
+    };
+    let policies = match PolicyEngine::new(&config.policy) {
+        Ok(policies) => policies,
+        Err(err) => {
+            eprintln!("error: failed to load config: {}", err);
+            std::process::exit(2);
+        }
+    };
+    let redactor = match cli
+        .redact
+        .then(|| Redactor::new(cli.redact_salt.as_deref()))
+        .transpose()
+    {
+        Ok(redactor) => redactor,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(2);
+        }
+    };
 
-    if has_dirty {
-        std::process::exit(1);
+    let scan_options = ScanOptions {
+        timeout: cli.timeout,
+        max_depth: cli.max_depth,
+        is_one_file_system: cli.one_file_system,
+        should_follow_symlinks: cli.follow_symlinks,
+        vcs: cli.vcs.clone(),
+        nested: cli.nested,
+        max_duration: cli.max_duration,
+        sample: cli.sample,
+        sample_seed: cli.sample_seed,
+        check_remotes: cli.check_remotes,
+        untracked: cli.untracked,
+        max_files: cli.files.then_some(cli.max_files),
+        should_fetch: cli.fetch,
+        fetch_max_age: cli.fetch_max_age,
+    };
+
+    if let Some(command) = &cli.command {
+        // These run git commands in every repository they find.
+        let git_options = ScanOptions {
+            vcs: vec![VcsKind::Git],
+            ..scan_options.clone()
+        };
+        let result = match command {
+            Commands::Serve(args) => serve::run(args, scan_options, config, policies, redactor),
+            Commands::Archive(args) => archive::run(args, &git_options),
+            Commands::Migrate(args) => migrate::run(args, &git_options),
+            Commands::Maintain(args) => maintain::run(args, &git_options),
+            Commands::Orphans(args) => orphans::run(args, &git_options),
+            Commands::Diff(args) => {
+                diff::run(args, &config, &policies, &scan_options, redactor.as_ref())
+            }
+            Commands::Daemon(args) => {
+                daemon::run(args, &config, &policies, &scan_options, redactor.as_ref())
+            }
+            Commands::Status(args) => {
+                daemon::status(args, &config, &policies, &scan_options, redactor.as_ref())
+            }
+            Commands::Triage(args) => triage::run(args, &config, &git_options, redactor.as_ref()),
+            Commands::Completions(args) => generate::completions(args),
+            Commands::Manpages(args) => generate::manpages(args),
+            Commands::New(args) => bootstrap::run(args, &config.templates, &policies),
+            Commands::Verify(args) => verify::run(args, &git_options),
+            Commands::Check(args) => check::run(
+                args,
+                &config.check,
+                &policies,
+                &scan_options,
+                redactor.as_ref(),
+            ),
+            Commands::Watch(args) => {
+                watch::run(args, &config, &policies, &scan_options, redactor.as_ref())
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("error: {}", err);
+            std::process::exit(2);
+        }
+        return;
     }
-}
 
-fn scan_root(root: &Path) -> Vec<RepoStatus> {
-    let mut statuses = Vec::new();
-    let mut walker = WalkDir::new(root).follow_links(false).into_iter();
+    // Load the baseline before scanning so a bad path fails fast.
+    let baseline = match cli.assert_no_regression.as_deref().map(baseline::load) {
+        Some(Ok(baseline)) => Some(baseline),
+        Some(Err(err)) => {
+            eprintln!("error: failed to load baseline: {}", err);
+            std::process::exit(2);
+        }
+        None => None,
+    };
 
-    while let Some(entry) = walker.next() {
-        let entry = match entry {
-            Ok(entry) => entry,
-            Err(_) => continue,
-        };
+    if let Err(err) = ctrlc::set_handler(git::cancel) {
+        eprintln!("warning: failed to install Ctrl-C handler: {}", err);
+    }
 
-        if is_git_marker(&entry) {
-            let repo_root = entry.path().parent().unwrap_or(entry.path()).to_path_buf();
-            statuses.push(get_repo_status(&repo_root));
+    // Only for people watching a terminal; piped and JSON output stay clean.
+    let progress = Arc::new(ScanProgress::default());
+    let display = (!cli.quiet && !cli.json && std::io::stdout().is_terminal())
+        .then(|| ProgressDisplay::start(Arc::clone(&progress)));
+    let mut scan = scan_root_with_progress(&cli.root, &scan_options, &progress);
+    for target in cli.docker.iter().chain(&cli.ssh) {
+        scan.merge(scan_remote(target, &scan_options));
+    }
+    if let Some(display) = display {
+        display.finish();
+    }
+    if git::is_cancelled() {
+        eprintln!("interrupted");
+        std::process::exit(130);
+    }
+    apply_config(
+        &mut scan.statuses,
+        &config,
+        &policies,
+        &scan_options,
+        !cli.no_checks,
+    );
+    if let Err(err) = manifest::attach_metadata(&mut scan.statuses) {
+        eprintln!("warning: failed to read repository metadata: {}", err);
+    }
+    if cli.enrich {
+        enrich::enrich(&mut scan.statuses, &config.enrich);
+    }
+    if cli.check_deps {
+        deps::check(&mut scan.statuses);
+    }
+    if cli.sizes {
+        sizes::measure(&mut scan.statuses);
+    }
+    if cli.audit_encryption {
+        volume::audit(&mut scan.statuses);
+    }
+    if cli.sort_by_size {
+        scan.statuses.sort_by_key(|status| {
+            std::cmp::Reverse(status.sizes.as_ref().map(RepoSizes::total_bytes))
+        });
+    }
+    if !cli.only_host.is_empty() {
+        scan.statuses.retain(|status| {
+            status
+                .remote
+                .as_ref()
+                .and_then(|remote| remote.host.as_deref())
+                .is_some_and(|host| {
+                    cli.only_host
+                        .iter()
+                        .any(|only| only.eq_ignore_ascii_case(host))
+                })
+        });
+    }
+    if cli.only_no_remote {
+        scan.statuses.retain(|status| status.remotes.is_empty());
+    }
+    if let Some(days) = cli.stale_days {
+        let cutoff = unix_now().saturating_sub(days.saturating_mul(SECONDS_PER_DAY));
+        scan.statuses.retain(|status| {
+            status.is_dirty && status.last_touched.is_some_and(|touched| touched < cutoff)
+        });
+        // Oldest forgotten work first.
+        scan.statuses.sort_by_key(|status| status.last_touched);
+    }
+    if let Some(key) = cli.sort {
+        grouping::sort(&mut scan.statuses, key);
+    }
+    let mut groups = cli
+        .group_by
+        .map(|by| grouping::group(&mut scan.statuses, by));
+    if let Some(redactor) = &redactor {
+        redactor.redact_scan(&mut scan);
+        for group in groups.iter_mut().flatten() {
+            *group = redactor.redact_group(group);
         }
+    }
+    let statuses = &scan.statuses;
 
-        if entry.file_type().is_dir() && entry.file_name() == OsStr::new(".git") {
-            walker.skip_current_dir();
+    let summary = summarize(statuses);
+    // Filtered, narrowed, sampled, remote or cut-short scans would not compare like
+    // for like.
+    let is_full_scan = cli.sample.is_none()
+        && cli.docker.is_empty()
+        && cli.ssh.is_empty()
+        && cli.only_host.is_empty()
+        && !cli.only_no_remote
+        && cli.stale_days.is_none()
+        && cli.max_depth.is_none()
+        && !cli.one_file_system
+        && !cli.follow_symlinks
+        && cli.vcs == [VcsKind::Git]
+        && cli.nested == NestedPolicy::Include
+        && cli.untracked.is_none()
+        && scan.unscanned.is_empty();
+    let trend = if !cli.no_trend && is_full_scan {
+        trend::record(&cli.root, &summary).unwrap_or_else(|err| {
+            eprintln!("warning: failed to record scan totals: {}", err);
+            None
+        })
+    } else {
+        None
+    };
+    let top = cli
+        .top
+        .map(|count| top_offenders(statuses, count, cli.top_by));
+    let sample_report = scan
+        .sample
+        .map(|info| sample::report(info, statuses, &summary));
+    let mut specs = if !cli.sink.is_empty() {
+        cli.sink.clone()
+    } else if !config.sinks.is_empty() {
+        config.sinks.clone()
+    } else if cli.json {
+        vec![SinkSpec::Json(cli.output.clone())]
+    } else if cli.paths_only {
+        vec![SinkSpec::Paths(cli.output.clone())]
+    } else {
+        vec![SinkSpec::Human(cli.output.clone())]
+    };
+    if cli.quiet {
+        specs.retain(|spec| !spec.is_stdout());
+    }
+    // JSON reports carry the errors themselves.
+    if !cli.quiet && !specs.iter().any(SinkSpec::is_json) {
+        for error in &scan.errors {
+            eprintln!("error: {}: {}", error.path.display(), error.message);
+        }
+    }
+    let report = Report {
+        statuses,
+        errors: &scan.errors,
+        unscanned: &scan.unscanned,
+        summary: &summary,
+        sample: sample_report.as_ref(),
+        top: top.as_ref(),
+        groups: groups.as_deref(),
+        show_clean: cli.show_clean,
+        should_show_idle: cli.stale_days.is_some(),
+        is_summary_only: cli.summary,
+        is_nul_terminated: cli.is_nul_terminated,
+        trend: trend.as_ref(),
+        redactor: redactor.as_ref(),
+    };
+    // Sinks given on the command line replace those in the config, webhooks included.
+    let configured_webhooks: &[_] = if cli.sink.is_empty() {
+        &config.webhooks
+    } else {
+        &[]
+    };
+    let sinks = match sink::build(
+        &specs,
+        &cli.encrypt_to,
+        configured_webhooks,
+        cli.color,
+        &config.theme,
+    ) {
+        Ok(sinks) => sinks,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(2);
+        }
+    };
+    for sink in &sinks {
+        if let Err(err) = sink.emit(&report) {
+            eprintln!("error: failed to write report: {}", err);
+            std::process::exit(2);
         }
     }
 
-    statuses
+    if cli.strict && !scan.errors.is_empty() {
+        std::process::exit(2);
+    }
+    // Conflicts block everything else in the repo, so they outrank dirt and regressions.
+    if cli.fail_on_conflicts && summary.repos_with_conflicts > 0 {
+        std::process::exit(3);
+    }
+    if let Some(baseline) = &baseline {
+        // Stderr, so a `--json` report on stdout stays parseable.
+        let regressions = baseline.regressions(statuses);
+        for regression in regressions.iter().filter(|_| !cli.quiet) {
+            eprintln!(
+                "regression: {} ({})",
+                regression.status.path.display(),
+                regression.reasons.join(", ")
+            );
+        }
+        if !regressions.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if summary.dirty > 0 {
+        std::process::exit(1);
+    }
 }
 
-fn is_git_marker(entry: &DirEntry) -> bool {
-    if entry.file_name() != OsStr::new(".git") {
-        return false;
+/// Runs fresh statuses through the per-repo overrides, the policies and `checks.d`, then
+/// scores them, so every command reports a repo the same way a plain scan does.
+fn apply_config(
+    statuses: &mut Vec<RepoStatus>,
+    config: &Config,
+    policies: &PolicyEngine,
+    options: &ScanOptions,
+    should_run_checks: bool,
+) {
+    overrides::apply(&config.repos, statuses);
+    policies.apply(statuses, options.timeout);
+    if should_run_checks {
+        checks::run(statuses, options.timeout);
     }
+    score::apply(&config.score, statuses);
+}
 
-    entry.file_type().is_dir() || entry.file_type().is_file()
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
 }
 
-fn get_repo_status(repo_root: &Path) -> RepoStatus {
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_root)
-        .arg("status")
-        .arg("--porcelain=2")
-        .arg("-b")
-        .output();
+/// Parses durations like `500ms`, `10s`, `5m`, `1h` or `2d`; bare numbers are seconds.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", value))?;
 
-    match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let mut uncommitted_changes = 0;
-            let mut unpushed_commits = 0;
-            let mut has_upstream = false;
+    let unit_seconds: u64 = match unit {
+        "ms" => return Ok(Duration::from_millis(number)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => SECONDS_PER_DAY,
+        _ => return Err(format!("unknown duration unit `{}` in `{}`", unit, value)),
+    };
+    number
+        .checked_mul(unit_seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration `{}` is too long", value))
+}
 
-            for line in stdout.lines() {
-                if line.starts_with("# branch.upstream ") {
-                    has_upstream = true;
-                    continue;
+impl TopMetric {
+    fn value(self, status: &RepoStatus) -> f64 {
+        match self {
+            TopMetric::Score => status.score,
+            TopMetric::Unpushed => status.unpushed_commits as f64,
+            TopMetric::Uncommitted => status.uncommitted_changes as f64,
+            // Clean repos have nothing to lose, however long they sit.
+            TopMetric::Age => match status.last_touched {
+                Some(touched) if status.is_dirty => {
+                    let days = unix_now().saturating_sub(touched) as f64 / SECONDS_PER_DAY as f64;
+                    (days * 10.0).round() / 10.0
                 }
+                _ => 0.0,
+            },
+        }
+    }
 
-                if let Some(rest) = line.strip_prefix("# branch.ab ") {
-                    for part in rest.split_whitespace() {
-                        if let Some(ahead) = part.strip_prefix('+') {
-                            if let Ok(value) = ahead.parse::<usize>() {
-                                unpushed_commits = value;
-                            }
-                        }
-                    }
-                    continue;
-                }
+    fn label(self) -> &'static str {
+        match self {
+            TopMetric::Score => "score",
+            TopMetric::Unpushed => "unpushed",
+            TopMetric::Uncommitted => "uncommitted",
+            TopMetric::Age => "days idle",
+        }
+    }
+}
 
-                if line.starts_with("1 ")
-                    || line.starts_with("2 ")
-                    || line.starts_with("u ")
-                    || line.starts_with("? ")
-                {
-                    uncommitted_changes += 1;
-                }
-            }
+fn summarize(statuses: &[RepoStatus]) -> Summary {
+    let dirty = statuses.iter().filter(|status| status.is_dirty).count();
+    Summary {
+        total: statuses.len(),
+        dirty,
+        clean: statuses.len().saturating_sub(dirty),
+        repos_with_uncommitted: statuses
+            .iter()
+            .filter(|status| status.uncommitted_changes > 0)
+            .count(),
+        repos_with_unpushed: statuses
+            .iter()
+            .filter(|status| status.unpushed_commits > 0)
+            .count(),
+        repos_with_conflicts: statuses
+            .iter()
+            .filter(|status| status.conflicts > 0)
+            .count(),
+        repos_without_remote: statuses
+            .iter()
+            .filter(|status| status.remotes.is_empty())
+            .count(),
+        policy_violations: statuses
+            .iter()
+            .map(|status| status.policy_violations.len())
+            .sum(),
+        repos_on_unencrypted_volumes: statuses
+            .iter()
+            .filter(|status| {
+                status
+                    .volume
+                    .as_ref()
+                    .is_some_and(|volume| volume.is_encrypted == Some(false))
+            })
+            .count(),
+    }
+}
+
+/// Ranks repos by the metric, dropping those that score zero.
+fn top_offenders(statuses: &[RepoStatus], count: usize, metric: TopMetric) -> TopList<'_> {
+    let mut repos: Vec<&RepoStatus> = statuses
+        .iter()
+        .filter(|status| status.is_dirty && metric.value(status) > 0.0)
+        .collect();
+    repos.sort_by(|a, b| metric.value(b).total_cmp(&metric.value(a)));
+    repos.truncate(count);
+    TopList { metric, repos }
+}
 
-            let is_dirty = uncommitted_changes > 0 || unpushed_commits > 0;
-            RepoStatus {
-                path: repo_root.to_path_buf(),
-                is_dirty,
-                uncommitted_changes,
-                unpushed_commits,
-                has_upstream,
+fn short_commit(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+fn bare_note(status: &RepoStatus) -> &'static str {
+    if status.is_bare { " [bare]" } else { "" }
+}
+
+fn render_human(report: &Report, theme: Option<&Theme>) -> String {
+    let Report {
+        statuses,
+        unscanned,
+        sample,
+        groups,
+        show_clean,
+        should_show_idle,
+        top,
+        ..
+    } = *report;
+    let paint = |pick: fn(&Theme) -> &Style, text: &str| match theme {
+        Some(theme) => pick(theme).paint(text),
+        None => text.to_string(),
+    };
+    let mut out = String::new();
+    if report.is_summary_only {
+        write_categories(&mut out, statuses);
+        write_summary(&mut out, report.summary, report.trend);
+        return out;
+    }
+    let now = unix_now();
+    let mut current_group = None;
+    // With `--top`, its ranking below is the whole list.
+    let listed = if top.is_some() { &[][..] } else { statuses };
+    for (index, status) in listed.iter().enumerate() {
+        let group = groups.and_then(|groups| groups.get(index));
+        if is_listed(status, show_clean) && group.is_some() && group != current_group {
+            let _ = writeln!(out, "[{}]", group.map(String::as_str).unwrap_or_default());
+            current_group = group;
+        }
+        let idle_note = match status.last_touched {
+            Some(touched) if should_show_idle => {
+                let days = now.saturating_sub(touched) / SECONDS_PER_DAY;
+                format!(", idle: {} days", days)
             }
+            _ => String::new(),
+        };
+        let unpushed = format!("unpushed: {} commits", status.unpushed_commits);
+        let unpushed = if status.unpushed_commits > 0 {
+            paint(|theme| &theme.unpushed, &unpushed)
+        } else {
+            unpushed
+        };
+        let conflict_note = if status.conflicts > 0 {
+            format!("conflicts: {} files, ", status.conflicts)
+        } else {
+            String::new()
+        };
+        // Unresolved conflicts trump every other label.
+        let label = |pick: fn(&Theme) -> &Style, text: &str| {
+            if status.conflicts > 0 {
+                paint(|theme| &theme.conflicts, "CONFLICTS:")
+            } else {
+                paint(pick, text)
+            }
+        };
+        // Nothing in these is backed up anywhere, so they are listed even when clean.
+        if status.remotes.is_empty() {
+            let _ = writeln!(
+                out,
+                "{} {}{} ({}uncommitted: {} files, {}{})",
+                label(|theme| &theme.no_remote, "no_remote:"),
+                status.path.display(),
+                bare_note(status),
+                conflict_note,
+                status.uncommitted_changes,
+                unpushed,
+                idle_note
+            );
+        } else if status.is_dirty {
+            let upstream_note = if !status.has_upstream {
+                ", upstream: none".to_string()
+            } else if status.behind_commits > 0 {
+                format!(", behind: {} commits", status.behind_commits)
+            } else {
+                String::new()
+            };
+            let _ = writeln!(
+                out,
+                "{} {}{} ({}uncommitted: {} files, {}{}{})",
+                label(|theme| &theme.dirty, "dirty:"),
+                status.path.display(),
+                bare_note(status),
+                conflict_note,
+                status.uncommitted_changes,
+                unpushed,
+                upstream_note,
+                idle_note
+            );
+        } else if show_clean {
+            let _ = writeln!(
+                out,
+                "{} {}{}",
+                paint(|theme| &theme.clean, "clean:"),
+                status.path.display(),
+                bare_note(status)
+            );
+        }
+        if is_listed(status, show_clean) {
+            write_files(&mut out, status);
         }
-        Err(_) => RepoStatus {
-            path: repo_root.to_path_buf(),
-            is_dirty: false,
-            uncommitted_changes: 0,
-            unpushed_commits: 0,
-            has_upstream: false,
-        },
     }
-}
 
-fn print_human(statuses: &[RepoStatus], show_clean: bool) {
     for status in statuses {
-        if status.is_dirty {
-            let upstream_note = if status.has_upstream {
+        let behind: Vec<String> = status
+            .remote_ahead
+            .iter()
+            .filter(|remote| remote.ahead > 0 || !remote.has_branch)
+            .map(|remote| {
+                let branch_note = if remote.has_branch { "" } else { ", no branch" };
+                format!("{}: {} commits{}", remote.remote, remote.ahead, branch_note)
+            })
+            .collect();
+        if !behind.is_empty() {
+            let _ = writeln!(
+                out,
+                "remotes missing commits: {} ({})",
+                status.path.display(),
+                behind.join("; ")
+            );
+        }
+    }
+
+    for status in statuses {
+        for drift in &status.dependency_drift {
+            let behind = match drift.behind {
+                Some(behind) => format!("{} commits behind", behind),
+                None => "not found".to_string(),
+            };
+            let _ = writeln!(
+                out,
+                "drift: {} ({} pins {} of {}, {})",
+                status.path.display(),
+                drift.name,
+                short_commit(&drift.pinned),
+                drift.repo.display(),
+                behind
+            );
+        }
+        if let Some(sizes) = &status.sizes {
+            let gc_note = if sizes.should_gc {
+                ", gc recommended"
+            } else {
                 ""
+            };
+            let _ = writeln!(
+                out,
+                "size: {} (worktree {}, .git {}, {} loose objects, {} packs{})",
+                status.path.display(),
+                sizes::format_bytes(sizes.worktree_bytes),
+                sizes::format_bytes(sizes.git_dir_bytes),
+                sizes.loose_objects,
+                sizes.packs,
+                gc_note
+            );
+        }
+        if let Some(enrichment) = &status.enrichment {
+            let mut notes = Vec::new();
+            match enrichment.remote_state {
+                RemoteState::Active => {}
+                RemoteState::Archived => notes.push("remote archived".to_string()),
+                RemoteState::Deleted => notes.push("remote deleted".to_string()),
+            }
+            if let Some(request) = &enrichment.open_request {
+                notes.push(format!("open request #{}: {}", request.number, request.url));
+            }
+            if !notes.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "forge: {} ({})",
+                    status.path.display(),
+                    notes.join(", ")
+                );
+            }
+        }
+        match &status.volume {
+            Some(volume) if volume.is_encrypted == Some(false) => {
+                let _ = writeln!(
+                    out,
+                    "unencrypted: {} ({})",
+                    status.path.display(),
+                    volume::describe(volume)
+                );
+            }
+            Some(volume) if volume.is_encrypted.is_none() => {
+                let _ = writeln!(
+                    out,
+                    "encryption unknown: {} ({})",
+                    status.path.display(),
+                    volume::describe(volume)
+                );
+            }
+            _ => {}
+        }
+        if let Some(service) = status.sync_service {
+            let _ = writeln!(
+                out,
+                "sync: {} (inside a {} folder, which can corrupt .git)",
+                status.path.display(),
+                service
+            );
+        }
+        if let Some(lfs) = status.lfs.as_ref().filter(|lfs| lfs.needs_attention()) {
+            let problem = if !lfs.is_installed {
+                "git-lfs not installed".to_string()
+            } else if let Some(pending) = lfs.pending_uploads.filter(|&n| n > 0) {
+                format!("{} objects pending upload", pending)
             } else {
-                ", upstream: none"
+                "pre-push hook missing, run `git lfs install`".to_string()
             };
-            println!(
-                "dirty: {} (uncommitted: {} files, unpushed: {} commits{})",
+            let _ = writeln!(out, "lfs: {} ({})", status.path.display(), problem);
+        }
+        for violation in &status.policy_violations {
+            let _ = writeln!(
+                out,
+                "policy: {} ({}: {})",
                 status.path.display(),
-                status.uncommitted_changes,
-                status.unpushed_commits,
-                upstream_note
+                violation.policy,
+                violation.message
             );
-        } else if show_clean {
-            println!("clean: {}", status.path.display());
+        }
+        for (check, findings) in &status.findings {
+            let findings: Vec<String> = findings
+                .iter()
+                .map(|(key, value)| match value {
+                    serde_json::Value::String(text) => format!("{}={}", key, text),
+                    value => format!("{}={}", key, value),
+                })
+                .collect();
+            let _ = writeln!(
+                out,
+                "check: {} ({}: {})",
+                status.path.display(),
+                check,
+                findings.join(", ")
+            );
+        }
+    }
+
+    // Totals cover every repo, also when `--top` lists only some.
+    let summary = report.summary;
+    if !show_clean && summary.dirty == 0 {
+        let _ = writeln!(out, "no repositories with local changes found");
+    }
+
+    write_summary(&mut out, summary, report.trend);
+
+    if let Some(top) = top {
+        let _ = writeln!(out, "top {} by {}:", top.repos.len(), top.metric.label());
+        for (rank, status) in top.repos.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "  {}. {} ({}: {})",
+                rank + 1,
+                status.path.display(),
+                top.metric.label(),
+                top.metric.value(status)
+            );
+        }
+    }
+
+    if let Some(sample) = sample {
+        let _ = writeln!(
+            out,
+            "sampled {} of {} repositories; estimated totals ({:.0}% confidence):",
+            sample.sampled,
+            sample.discovered,
+            sample.confidence * 100.0
+        );
+        let estimates = [
+            ("dirty", &sample.estimates.dirty),
+            (
+                "with uncommitted changes",
+                &sample.estimates.repos_with_uncommitted,
+            ),
+            (
+                "with unpushed commits",
+                &sample.estimates.repos_with_unpushed,
+            ),
+        ];
+        for (label, estimate) in estimates {
+            let _ = writeln!(
+                out,
+                "  {}: {} ({}-{})",
+                label, estimate.estimate, estimate.low, estimate.high
+            );
+        }
+    }
+
+    if !unscanned.is_empty() {
+        let _ = writeln!(
+            out,
+            "{} paths unscanned (time budget exceeded):",
+            unscanned.len()
+        );
+        for path in unscanned {
+            let _ = writeln!(out, "  {}", path.display());
         }
     }
+    out
 }
 
-fn print_json(statuses: &[RepoStatus]) {
+fn write_summary(out: &mut String, summary: &Summary, trend: Option<&Trend>) {
+    // `▲2`/`▼2` after a count when an earlier scan of this root is on record.
+    let change = |pick: fn(&Trend, usize) -> String, now: usize| match trend {
+        Some(trend) => format!(" {}", pick(trend, now)),
+        None => String::new(),
+    };
+    let _ = writeln!(out, "scanned {} repositories", summary.total);
+    let _ = writeln!(
+        out,
+        "dirty: {}{}{}, clean: {}",
+        summary.dirty,
+        change(Trend::dirty, summary.dirty),
+        trend
+            .map(|trend| format!(" {}", trend.label()))
+            .unwrap_or_default(),
+        summary.clean
+    );
+    let _ = writeln!(
+        out,
+        "repos with uncommitted changes: {}{}, unpushed commits: {}{}",
+        summary.repos_with_uncommitted,
+        change(Trend::uncommitted, summary.repos_with_uncommitted),
+        summary.repos_with_unpushed,
+        change(Trend::unpushed, summary.repos_with_unpushed)
+    );
+    if summary.repos_with_conflicts > 0 {
+        let _ = writeln!(
+            out,
+            "repos with conflicts: {}",
+            summary.repos_with_conflicts
+        );
+    }
+    if summary.policy_violations > 0 {
+        let _ = writeln!(out, "policy violations: {}", summary.policy_violations);
+    }
+    if summary.repos_on_unencrypted_volumes > 0 {
+        let _ = writeln!(
+            out,
+            "repos on unencrypted volumes: {}",
+            summary.repos_on_unencrypted_volumes
+        );
+    }
+    if summary.repos_without_remote > 0 {
+        let _ = writeln!(
+            out,
+            "repos without a remote: {}{}",
+            summary.repos_without_remote,
+            change(Trend::without_remote, summary.repos_without_remote)
+        );
+    }
+    if let Some(sparkline) = trend.and_then(Trend::sparkline) {
+        let _ = writeln!(out, "dirty by day: {}", sparkline);
+    }
+}
+
+/// A `--summary` category: its label and which repos belong to it.
+type Category = (&'static str, fn(&RepoStatus) -> bool);
+
+/// One line per non-empty category, naming its repositories.
+fn write_categories(out: &mut String, statuses: &[RepoStatus]) {
+    let categories: [Category; 5] = [
+        ("conflicts", |status| status.conflicts > 0),
+        ("uncommitted", |status| status.uncommitted_changes > 0),
+        ("unpushed", |status| status.unpushed_commits > 0),
+        ("no remote", |status| status.remotes.is_empty()),
+        ("policy violations", |status| {
+            !status.policy_violations.is_empty()
+        }),
+    ];
+    for (label, is_member) in categories {
+        let members: Vec<String> = statuses
+            .iter()
+            .filter(|status| is_member(status))
+            .map(|status| status.path.display().to_string())
+            .collect();
+        if !members.is_empty() {
+            let _ = writeln!(out, "{} ({}): {}", label, members.len(), members.join(", "));
+        }
+    }
+}
+
+/// The `--files` list under a repo's line, noting how many files it left out.
+fn write_files(out: &mut String, status: &RepoStatus) {
+    for file in &status.files {
+        let _ = writeln!(out, "    {} {}", file.code, file.path.display());
+    }
+    let unlisted = status
+        .uncommitted_changes
+        .saturating_sub(status.files.len());
+    if !status.files.is_empty() && unlisted > 0 {
+        let _ = writeln!(out, "    ... and {} more", unlisted);
+    }
+}
+
+/// Repos without a remote are listed even when clean; nothing in them is backed up.
+fn is_listed(status: &RepoStatus, show_clean: bool) -> bool {
+    status.remotes.is_empty() || status.is_dirty || show_clean
+}
+
+fn render_paths(report: &Report) -> String {
+    let terminator = if report.is_nul_terminated { '\0' } else { '\n' };
+    let mut out = String::new();
+    let statuses: Vec<&RepoStatus> = match report.top {
+        Some(top) => top.repos.clone(),
+        None => report.statuses.iter().collect(),
+    };
+    for status in statuses {
+        if is_listed(status, report.show_clean) {
+            out.push_str(&status.path.to_string_lossy());
+            out.push(terminator);
+        }
+    }
+    out
+}
+
+fn render_json(report: &Report) -> String {
+    if report.is_summary_only {
+        let output = SummaryOutput {
+            schema_version: SCHEMA_VERSION,
+            summary: report.summary,
+        };
+        let json = serde_json::to_string_pretty(&output).unwrap_or_else(|_| "{}".to_string());
+        return format!("{}\n", json);
+    }
     let output = JsonOutput {
-        total: statuses.len(),
-        repos: statuses,
+        schema_version: SCHEMA_VERSION,
+        total: report.summary.total,
+        repos: report.statuses,
+        errors: report.errors,
+        unscanned: report.unscanned,
+        sample: report.sample,
+        top: report.top,
     };
     let json = serde_json::to_string_pretty(&output).unwrap_or_else(|_| "{}".to_string());
-    println!("{}", json);
+    format!("{}\n", json)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("10s"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration(" 2h "), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86_400)));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("-5s").is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert!(parse_duration("999999999999999999d").is_err());
+        assert!(parse_duration(&format!("{}h", u64::MAX / 60)).is_err());
+        assert_eq!(
+            parse_duration(&format!("{}s", u64::MAX)),
+            Ok(Duration::from_secs(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn ranks_dirty_repos_by_age() {
+        let days_ago = |days: u64| Some(unix_now() - days * SECONDS_PER_DAY);
+        let repo = |path: &str, is_dirty: bool, last_touched: Option<u64>| RepoStatus {
+            is_dirty,
+            last_touched,
+            ..vcs::empty_status(Path::new(path), VcsKind::Git)
+        };
+        let statuses = [
+            repo("recent", true, days_ago(2)),
+            repo("clean", false, days_ago(90)),
+            repo("old", true, days_ago(30)),
+            repo("unknown", true, None),
+        ];
+
+        let top = top_offenders(&statuses, 5, TopMetric::Age);
+        let paths: Vec<&Path> = top
+            .repos
+            .iter()
+            .map(|status| status.path.as_path())
+            .collect();
+        assert_eq!(paths, [Path::new("old"), Path::new("recent")]);
+        assert_eq!(TopMetric::Age.value(&statuses[2]), 30.0);
+    }
 }
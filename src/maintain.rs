@@ -0,0 +1,78 @@
+use std::io;
+use std::path::Path;
+
+use crate::git::run_git;
+use crate::{MaintainArgs, ScanOptions, pool, scan_root};
+
+/// The steps run in one repository, in order, with the first failure if any.
+struct Outcome {
+    steps: Vec<&'static str>,
+    failure: Option<(&'static str, io::Error)>,
+}
+
+pub fn run(args: &MaintainArgs, options: &ScanOptions) -> io::Result<()> {
+    let scan = scan_root(&args.root, options);
+    for error in &scan.errors {
+        println!("error: {} ({})", error.path.display(), error.message);
+    }
+
+    let repos: Vec<&Path> = scan
+        .statuses
+        .iter()
+        .map(|status| status.path.as_path())
+        .collect();
+    let workers = args.jobs.unwrap_or_else(pool::default_workers);
+    let outcomes = pool::map(&repos, workers, |repo| maintain_repo(repo, args));
+
+    let mut failed = 0;
+    for (repo, outcome) in repos.iter().zip(&outcomes) {
+        match &outcome.failure {
+            None => println!("ok: {} ({})", repo.display(), outcome.steps.join(", ")),
+            Some((step, err)) => {
+                failed += 1;
+                println!("failed: {} ({}: {})", repo.display(), step, err);
+            }
+        }
+    }
+
+    println!(
+        "maintained {} of {} repositories",
+        repos.len() - failed,
+        repos.len()
+    );
+    if failed > 0 || !scan.errors.is_empty() {
+        return Err(io::Error::other(format!(
+            "{} repositories failed maintenance",
+            failed + scan.errors.len()
+        )));
+    }
+    Ok(())
+}
+
+fn maintain_repo(repo: &Path, args: &MaintainArgs) -> Outcome {
+    let mut outcome = Outcome {
+        steps: Vec::new(),
+        failure: None,
+    };
+    let mut step = |name: &'static str, git_args: &[&str]| {
+        if outcome.failure.is_some() {
+            return;
+        }
+        match run_git(repo, git_args) {
+            Ok(_) => outcome.steps.push(name),
+            Err(err) => outcome.failure = Some((name, err)),
+        }
+    };
+
+    step("gc", &["gc", "--auto", "--quiet"]);
+    // `git remote prune` fails outright when the remote does not exist.
+    let has_origin = run_git(repo, &["remote"])
+        .is_ok_and(|remotes| remotes.lines().any(|name| name == "origin"));
+    if has_origin {
+        step("prune origin", &["remote", "prune", "origin"]);
+    }
+    if args.maintenance {
+        step("maintenance", &["maintenance", "run", "--quiet"]);
+    }
+    outcome
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::RepoStatus;
+use crate::config::config_dir;
+
+/// Repositories gittracker created or was told about, kept next to `config.toml`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub repos: Vec<ManifestRepo>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ManifestRepo {
+    pub path: PathBuf,
+    pub remote: Option<String>,
+    /// Free-form fields such as a cost center, copied into JSON output as they are.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+}
+
+pub fn path() -> io::Result<PathBuf> {
+    config_dir()
+        .map(|dir| dir.join("manifest.toml"))
+        .ok_or_else(|| io::Error::other("cannot locate the config directory; set HOME"))
+}
+
+pub fn load(path: &Path) -> io::Result<Manifest> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Manifest::default()),
+        Err(err) => return Err(err),
+    };
+    toml::from_str(&contents).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid manifest {}: {}", path.display(), err),
+        )
+    })
+}
+
+/// Adds or updates the entry for `repo`, keyed by its path.
+pub fn register(repo: &Path, remote: Option<&str>) -> io::Result<PathBuf> {
+    let path = path()?;
+    let mut manifest = load(&path)?;
+    // Metadata is maintained by hand, so re-registering a repo must keep it.
+    let metadata = manifest
+        .repos
+        .iter()
+        .position(|entry| entry.path == repo)
+        .map(|index| manifest.repos.remove(index).metadata)
+        .unwrap_or_default();
+    manifest.repos.push(ManifestRepo {
+        path: repo.to_path_buf(),
+        remote: remote.map(str::to_string),
+        metadata,
+    });
+    manifest.repos.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let contents = toml::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Copies each manifest entry's metadata onto the scanned repo at the same path.
+pub fn attach_metadata(statuses: &mut [RepoStatus]) -> io::Result<()> {
+    let manifest = load(&path()?)?;
+    let entries: Vec<(PathBuf, &ManifestRepo)> = manifest
+        .repos
+        .iter()
+        .filter(|entry| !entry.metadata.is_empty())
+        .map(|entry| (canonical(&entry.path), entry))
+        .collect();
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    for status in statuses {
+        let repo = canonical(&status.path);
+        if let Some((_, entry)) = entries.iter().find(|(path, _)| *path == repo) {
+            status.metadata = entry.metadata.clone();
+        }
+    }
+    Ok(())
+}
+
+/// Scanned paths are relative to the root argument while manifest paths are absolute.
+pub fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,219 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::git::{get_repo_status, run_git};
+use crate::{MigrateArgs, RepoStatus, ScanOptions, scan_root};
+
+const BUNDLE_NAME: &str = "gittracker-migrate.bundle";
+
+enum Outcome {
+    Migrated { branches: usize, stashes: usize },
+    Skipped(String),
+}
+
+pub fn run(args: &MigrateArgs, options: &ScanOptions) -> io::Result<()> {
+    let root = fs::canonicalize(&args.root)?;
+    let dest = std::path::absolute(&args.to)?;
+    let scan = scan_root(&root, options);
+
+    let mut migrated = 0;
+    let mut not_migrated = scan.errors.len();
+    for error in &scan.errors {
+        println!("error: {} ({})", error.path.display(), error.message);
+    }
+    for status in &scan.statuses {
+        let target = dest.join(relative_target(&root, &status.path));
+        match migrate_repo(status, &target, args.dry_run) {
+            Ok(Outcome::Migrated { branches, stashes }) => {
+                migrated += 1;
+                let verb = if args.dry_run {
+                    "would migrate"
+                } else {
+                    "migrated"
+                };
+                println!(
+                    "{}: {} -> {} ({} branches, {} stashes)",
+                    verb,
+                    status.path.display(),
+                    target.display(),
+                    branches,
+                    stashes
+                );
+            }
+            Ok(Outcome::Skipped(reason)) => {
+                not_migrated += 1;
+                println!("skipped: {} ({})", status.path.display(), reason);
+            }
+            Err(err) => {
+                not_migrated += 1;
+                println!("failed: {} ({})", status.path.display(), err);
+            }
+        }
+    }
+
+    let verb = if args.dry_run {
+        "would migrate"
+    } else {
+        "migrated"
+    };
+    println!(
+        "{} {} of {} repositories",
+        verb,
+        migrated,
+        scan.statuses.len() + scan.errors.len()
+    );
+    if not_migrated > 0 {
+        return Err(io::Error::other(format!(
+            "{} repositories were not migrated",
+            not_migrated
+        )));
+    }
+    Ok(())
+}
+
+fn relative_target(root: &Path, repo: &Path) -> PathBuf {
+    match repo.strip_prefix(root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative.to_path_buf(),
+        _ => PathBuf::from(repo.file_name().unwrap_or(repo.as_os_str())),
+    }
+}
+
+fn migrate_repo(status: &RepoStatus, target: &Path, dry_run: bool) -> io::Result<Outcome> {
+    if status.uncommitted_changes > 0 {
+        return Ok(Outcome::Skipped(format!(
+            "{} uncommitted files; commit or stash them first",
+            status.uncommitted_changes
+        )));
+    }
+    if target.exists() && fs::read_dir(target)?.next().is_some() {
+        return Ok(Outcome::Skipped(format!(
+            "{} already exists and is not empty",
+            target.display()
+        )));
+    }
+
+    let source = &status.path;
+    let branches = run_git(
+        source,
+        &["for-each-ref", "--format=%(refname)", "refs/heads"],
+    )?
+    .lines()
+    .count();
+    let stashes = stash_entries(source)?;
+    if dry_run {
+        return Ok(Outcome::Migrated {
+            branches,
+            stashes: stashes.len(),
+        });
+    }
+
+    fs::create_dir_all(target)?;
+    run_git(target, &["init", "--quiet"])?;
+    transfer_refs(source, target, &stashes)?;
+    copy_config(source, target)?;
+    checkout_head(source, target)?;
+    restore_stashes(target, &stashes)?;
+    verify(status, target)?;
+
+    Ok(Outcome::Migrated {
+        branches,
+        stashes: stashes.len(),
+    })
+}
+
+/// Stash entries as (commit, reflog message), newest first.
+fn stash_entries(repo: &Path) -> io::Result<Vec<(String, String)>> {
+    let list = run_git(repo, &["stash", "list", "--format=%H%x09%gs"])?;
+    Ok(list
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(hash, message)| (hash.to_string(), message.to_string()))
+        .collect())
+}
+
+/// Bundles every ref plus all stash entries, then unbundles into the target. Older
+/// stash entries have no ref of their own, so they are carried as extra revisions.
+fn transfer_refs(source: &Path, target: &Path, stashes: &[(String, String)]) -> io::Result<()> {
+    let bundle = target.join(".git").join(BUNDLE_NAME);
+    let bundle_arg = bundle.to_string_lossy().into_owned();
+
+    let mut create = vec!["bundle", "create", bundle_arg.as_str(), "--all"];
+    create.extend(stashes.iter().map(|(hash, _)| hash.as_str()));
+    run_git(source, &create)?;
+
+    let heads = run_git(target, &["bundle", "unbundle", &bundle_arg]);
+    let _ = fs::remove_file(&bundle);
+    for line in heads?.lines() {
+        let Some((hash, reference)) = line.split_once(' ') else {
+            continue;
+        };
+        if !reference.starts_with("refs/") {
+            continue;
+        }
+        run_git(target, &["update-ref", reference, hash])?;
+    }
+    Ok(())
+}
+
+/// Carries remotes and upstream tracking over so the copy reports the same state.
+fn copy_config(source: &Path, target: &Path) -> io::Result<()> {
+    // `git config --get-regexp` exits 1 when nothing matches, e.g. a repo without remotes.
+    let entries = run_git(
+        source,
+        &["config", "--local", "--get-regexp", r"^(remote|branch)\."],
+    )
+    .unwrap_or_default();
+
+    for line in entries.lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        run_git(target, &["config", "--local", "--add", key, value])?;
+    }
+    Ok(())
+}
+
+fn checkout_head(source: &Path, target: &Path) -> io::Result<()> {
+    match run_git(source, &["symbolic-ref", "--quiet", "HEAD"]) {
+        Ok(head) => {
+            let head = head.trim();
+            run_git(target, &["symbolic-ref", "HEAD", head])?;
+            if run_git(target, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok() {
+                run_git(target, &["reset", "--quiet", "--hard"])?;
+            }
+        }
+        Err(_) => {
+            let head = run_git(source, &["rev-parse", "HEAD"])?;
+            run_git(target, &["checkout", "--quiet", "--detach", head.trim()])?;
+        }
+    }
+    Ok(())
+}
+
+/// Rebuilds the stash reflog oldest-first so `stash@{N}` numbering matches the source.
+fn restore_stashes(target: &Path, stashes: &[(String, String)]) -> io::Result<()> {
+    if stashes.is_empty() {
+        return Ok(());
+    }
+
+    run_git(target, &["update-ref", "-d", "refs/stash"])?;
+    for (hash, message) in stashes.iter().rev() {
+        run_git(target, &["stash", "store", "-m", message, hash])?;
+    }
+    Ok(())
+}
+
+fn verify(before: &RepoStatus, target: &Path) -> io::Result<()> {
+    let after = get_repo_status(target, &ScanOptions::default())
+        .map_err(|err| io::Error::other(err.message))?;
+    if after.uncommitted_changes == 0
+        && after.unpushed_commits == before.unpushed_commits
+        && after.has_upstream == before.has_upstream
+    {
+        return Ok(());
+    }
+
+    Err(io::Error::other(format!(
+        "verification failed: {} uncommitted, {} unpushed (expected {})",
+        after.uncommitted_changes, after.unpushed_commits, before.unpushed_commits
+    )))
+}
@@ -0,0 +1,173 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+
+use crate::config::config_dir;
+use crate::{RepoStatus, unix_now};
+
+/// File in the config directory remembering since when repos have unpushed commits.
+const STATE_FILE: &str = "notify-state.json";
+
+/// Raises desktop notifications for repos whose state got worse between scans.
+pub struct Notifier {
+    stale_after: Option<Duration>,
+    previous: Option<HashMap<PathBuf, (bool, usize)>>,
+    /// Kept on disk with `stale_after`, so a restart doesn't reset how long work sat unpushed.
+    unpushed: BTreeMap<PathBuf, Unpushed>,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+struct Unpushed {
+    /// First scan that found unpushed commits, in seconds since the Unix epoch.
+    since: u64,
+    is_notified: bool,
+}
+
+impl Notifier {
+    pub fn new(stale_after: Option<Duration>) -> Self {
+        let unpushed = if stale_after.is_some() {
+            load_state().unwrap_or_else(|err| {
+                eprintln!("warning: failed to read notification state: {}", err);
+                BTreeMap::new()
+            })
+        } else {
+            BTreeMap::new()
+        };
+        Notifier {
+            stale_after,
+            previous: None,
+            unpushed,
+        }
+    }
+
+    /// Compares against the previous scan; the first scan only records a baseline.
+    pub fn observe(&mut self, statuses: &[RepoStatus]) {
+        let now = unix_now();
+        let mut current = HashMap::with_capacity(statuses.len());
+
+        for status in statuses {
+            current.insert(
+                status.path.clone(),
+                (status.is_dirty, status.unpushed_commits),
+            );
+            self.track_unpushed_age(status, now);
+
+            let Some(previous) = &self.previous else {
+                continue;
+            };
+            let (was_dirty, was_unpushed) =
+                previous.get(&status.path).copied().unwrap_or((false, 0));
+
+            if status.unpushed_commits > was_unpushed {
+                send(
+                    "Unpushed commits",
+                    &format!(
+                        "{}: {} unpushed commits",
+                        status.path.display(),
+                        status.unpushed_commits
+                    ),
+                );
+            } else if status.is_dirty && !was_dirty {
+                send(
+                    "Repository became dirty",
+                    &format!(
+                        "{}: {} uncommitted files",
+                        status.path.display(),
+                        status.uncommitted_changes
+                    ),
+                );
+            }
+        }
+        // Deleted or moved repos have nothing left to push from here.
+        self.unpushed.retain(|path, _| current.contains_key(path));
+
+        self.check_stale(now);
+        self.previous = Some(current);
+    }
+
+    fn track_unpushed_age(&mut self, status: &RepoStatus, now: u64) {
+        if status.unpushed_commits == 0 {
+            self.unpushed.remove(&status.path);
+            return;
+        }
+
+        self.unpushed
+            .entry(status.path.clone())
+            .or_insert(Unpushed {
+                since: now,
+                is_notified: false,
+            });
+    }
+
+    fn check_stale(&mut self, now: u64) {
+        let Some(stale_after) = self.stale_after else {
+            return;
+        };
+
+        for (path, unpushed) in &mut self.unpushed {
+            if now.saturating_sub(unpushed.since) < stale_after.as_secs() || unpushed.is_notified {
+                continue;
+            }
+
+            send(
+                "Unpushed work is getting stale",
+                &format!(
+                    "{}: unpushed for more than {}s",
+                    path.display(),
+                    stale_after.as_secs()
+                ),
+            );
+            unpushed.is_notified = true;
+        }
+        if let Err(err) = save_state(&self.unpushed) {
+            eprintln!("warning: failed to save notification state: {}", err);
+        }
+    }
+}
+
+fn state_path() -> io::Result<PathBuf> {
+    config_dir()
+        .map(|dir| dir.join(STATE_FILE))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))
+}
+
+fn load_state() -> io::Result<BTreeMap<PathBuf, Unpushed>> {
+    let path = state_path()?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(err),
+    };
+    serde_json::from_str(&contents).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid state file {}: {}", path.display(), err),
+        )
+    })
+}
+
+fn save_state(unpushed: &BTreeMap<PathBuf, Unpushed>) -> io::Result<()> {
+    let path = state_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string(unpushed).map_err(io::Error::other)?;
+    fs::write(path, contents)
+}
+
+fn send(summary: &str, body: &str) {
+    let result = Notification::new()
+        .appname("gittracker-rs")
+        .summary(summary)
+        .body(body)
+        .show();
+
+    if let Err(err) = result {
+        eprintln!("warning: failed to send notification: {}", err);
+    }
+}
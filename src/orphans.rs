@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+use crate::forge::{Forge, ForgeRepo};
+use crate::git::{normalize_url, remote_urls, run_git};
+use crate::{OrphansArgs, ScanOptions, manifest, scan_root};
+
+pub fn run(args: &OrphansArgs, options: &ScanOptions) -> io::Result<()> {
+    let forge = Forge::new(args.forge, args.api_url.as_deref(), &args.token);
+    let remote_repos = forge.org_repos(&args.org)?;
+
+    let mut local_urls = HashSet::new();
+    let mut scanned = 0;
+    for root in &args.roots {
+        let scan = scan_root(root, options);
+        for error in &scan.errors {
+            eprintln!("error: {}: {}", error.path.display(), error.message);
+        }
+        scanned += scan.statuses.len();
+        for status in &scan.statuses {
+            local_urls.extend(
+                remote_urls(&status.path)
+                    .iter()
+                    .map(|url| normalize_url(url)),
+            );
+        }
+    }
+
+    let missing: Vec<&ForgeRepo> = remote_repos
+        .iter()
+        .filter(|repo| args.include_archived || !repo.is_archived)
+        .filter(|repo| {
+            !local_urls.contains(&normalize_url(&repo.ssh_url))
+                && !local_urls.contains(&normalize_url(&repo.https_url))
+        })
+        .collect();
+
+    for repo in &missing {
+        let archived_note = if repo.is_archived { ", archived" } else { "" };
+        println!(
+            "missing: {} ({}{})",
+            repo.full_name,
+            clone_url(repo, args.https),
+            archived_note
+        );
+    }
+    println!(
+        "{} of {} repositories in {} have no local clone among {} scanned",
+        missing.len(),
+        remote_repos.len(),
+        args.org,
+        scanned
+    );
+
+    match &args.clone_missing {
+        Some(dest) => clone_missing(&missing, dest, args.https),
+        None => {
+            if !missing.is_empty() {
+                println!("run again with --clone-missing <DIR> to clone them");
+            }
+            Ok(())
+        }
+    }
+}
+
+fn clone_url(repo: &ForgeRepo, https: bool) -> &str {
+    if https {
+        &repo.https_url
+    } else {
+        &repo.ssh_url
+    }
+}
+
+/// Clones each repo into `dest/<name>` and registers it in the manifest; existing
+/// folders are left alone.
+fn clone_missing(repos: &[&ForgeRepo], dest: &Path, https: bool) -> io::Result<()> {
+    let dest = std::path::absolute(dest)?;
+    std::fs::create_dir_all(&dest)?;
+    let mut failed = 0;
+    for repo in repos {
+        let name = repo.full_name.rsplit('/').next().unwrap_or(&repo.full_name);
+        let target = dest.join(name);
+        if target.exists() {
+            println!(
+                "skipped: {} ({} already exists)",
+                repo.full_name,
+                target.display()
+            );
+            continue;
+        }
+
+        let url = clone_url(repo, https);
+        let target_arg = target.to_string_lossy();
+        match run_git(&dest, &["clone", "--quiet", url, &target_arg])
+            .and_then(|_| manifest::register(&target, Some(url)))
+        {
+            Ok(_) => println!("cloned: {} -> {}", repo.full_name, target.display()),
+            Err(err) => {
+                failed += 1;
+                println!("failed: {} ({})", repo.full_name, err);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(io::Error::other(format!(
+            "{} repositories failed to clone",
+            failed
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::RepoStatus;
+use crate::config::RepoConfig;
+use crate::manifest::canonical;
+
+/// Applies the `[repos."path"]` sections: drops ignored repos and clears `is_dirty` on
+/// repos whose changes stay within what their section tolerates.
+pub fn apply(overrides: &BTreeMap<String, RepoConfig>, statuses: &mut Vec<RepoStatus>) {
+    if overrides.is_empty() {
+        return;
+    }
+    let overrides: Vec<(PathBuf, &RepoConfig)> = overrides
+        .iter()
+        .map(|(path, repo)| (canonical(&expand_home(path)), repo))
+        .collect();
+
+    statuses.retain_mut(|status| {
+        let path = canonical(&status.path);
+        let Some((_, repo)) = overrides.iter().find(|(key, _)| *key == path) else {
+            return true;
+        };
+        if repo.ignore {
+            return false;
+        }
+        if repo.allow_untracked {
+            status.uncommitted_changes -= status.untracked_files;
+            status.untracked_files = 0;
+            status.files.retain(|file| !file.code.starts_with('?'));
+        }
+        // Conflicts and LFS objects that never reached the server are never tolerated.
+        let has_pending_uploads = status
+            .lfs
+            .as_ref()
+            .is_some_and(|lfs| lfs.pending_uploads.is_some_and(|n| n > 0));
+        if status.uncommitted_changes <= repo.max_uncommitted
+            && status.unpushed_commits <= repo.max_unpushed
+            && status.conflicts == 0
+            && !has_pending_uploads
+        {
+            status.is_dirty = false;
+        }
+        true
+    });
+}
+
+/// Config keys may start with `~/` for the home folder.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::ChangedFile;
+    use crate::vcs::{VcsKind, empty_status};
+
+    // Paths that don't exist canonicalize to themselves.
+    const REPO: &str = "/nonexistent/gittracker/legacy";
+
+    fn dirty(path: &Path, uncommitted_changes: usize, unpushed_commits: usize) -> RepoStatus {
+        RepoStatus {
+            is_dirty: true,
+            uncommitted_changes,
+            unpushed_commits,
+            ..empty_status(path, VcsKind::Git)
+        }
+    }
+
+    fn file(code: &str, path: &str) -> ChangedFile {
+        ChangedFile {
+            code: code.to_string(),
+            path: PathBuf::from(path),
+        }
+    }
+
+    fn apply_one(key: &str, repo: RepoConfig, status: RepoStatus) -> Option<RepoStatus> {
+        let overrides = BTreeMap::from([(key.to_string(), repo)]);
+        let mut statuses = vec![status];
+        apply(&overrides, &mut statuses);
+        statuses.pop()
+    }
+
+    #[test]
+    fn ignore_drops_the_repo() {
+        let repo = RepoConfig {
+            ignore: true,
+            ..RepoConfig::default()
+        };
+        assert!(apply_one(REPO, repo, dirty(Path::new(REPO), 1, 0)).is_none());
+    }
+
+    #[test]
+    fn leaves_other_repos_alone() {
+        let repo = RepoConfig {
+            ignore: true,
+            ..RepoConfig::default()
+        };
+        let status = apply_one(REPO, repo, dirty(Path::new("/nonexistent/other"), 1, 0));
+        assert!(status.is_some_and(|status| status.is_dirty));
+    }
+
+    #[test]
+    fn expands_home_in_keys() {
+        let Some(home) = std::env::var_os("HOME") else {
+            return;
+        };
+        let path = PathBuf::from(home).join("nonexistent-gittracker/legacy");
+        let repo = RepoConfig {
+            ignore: true,
+            ..RepoConfig::default()
+        };
+        assert!(apply_one("~/nonexistent-gittracker/legacy", repo, dirty(&path, 1, 0)).is_none());
+    }
+
+    #[test]
+    fn allow_untracked_subtracts_untracked_files() {
+        let repo = RepoConfig {
+            allow_untracked: true,
+            ..RepoConfig::default()
+        };
+        let status = RepoStatus {
+            untracked_files: 2,
+            files: vec![file("??", "a"), file("??", "b"), file(" M", "c")],
+            ..dirty(Path::new(REPO), 3, 0)
+        };
+        let status = apply_one(REPO, repo, status).unwrap();
+        assert_eq!(status.uncommitted_changes, 1);
+        assert_eq!(status.untracked_files, 0);
+        assert_eq!(status.files.len(), 1);
+        assert!(status.is_dirty);
+    }
+
+    #[test]
+    fn allow_untracked_cleans_a_repo_with_only_untracked_files() {
+        let repo = RepoConfig {
+            allow_untracked: true,
+            ..RepoConfig::default()
+        };
+        let status = RepoStatus {
+            untracked_files: 2,
+            ..dirty(Path::new(REPO), 2, 0)
+        };
+        assert!(!apply_one(REPO, repo, status).unwrap().is_dirty);
+    }
+
+    #[test]
+    fn tolerates_changes_up_to_the_limits() {
+        let repo = || RepoConfig {
+            max_uncommitted: 2,
+            max_unpushed: 1,
+            ..RepoConfig::default()
+        };
+        let within = apply_one(REPO, repo(), dirty(Path::new(REPO), 2, 1)).unwrap();
+        assert!(!within.is_dirty);
+        // Tolerated repos keep their counts.
+        assert_eq!(within.uncommitted_changes, 2);
+
+        let too_many_files = apply_one(REPO, repo(), dirty(Path::new(REPO), 3, 0)).unwrap();
+        assert!(too_many_files.is_dirty);
+        let too_many_commits = apply_one(REPO, repo(), dirty(Path::new(REPO), 0, 2)).unwrap();
+        assert!(too_many_commits.is_dirty);
+    }
+
+    #[test]
+    fn never_tolerates_conflicts() {
+        let repo = RepoConfig {
+            max_uncommitted: 10,
+            ..RepoConfig::default()
+        };
+        let status = RepoStatus {
+            conflicts: 1,
+            ..dirty(Path::new(REPO), 1, 0)
+        };
+        assert!(apply_one(REPO, repo, status).unwrap().is_dirty);
+    }
+}
@@ -0,0 +1,219 @@
+use std::io;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::RepoStatus;
+use crate::config::PolicyConfig;
+use crate::git;
+use crate::vcs::VcsKind;
+
+/// Conventional Commits subject: `type(scope)!: description`.
+const CONVENTIONAL_COMMIT: &str =
+    r"^(build|chore|ci|docs|feat|fix|perf|refactor|revert|style|test)(\([^)]+\))?!?: \S";
+
+/// A rule a repository broke, attached to its status.
+#[derive(Clone, Debug, Serialize)]
+pub struct PolicyViolation {
+    pub policy: &'static str,
+    pub message: String,
+}
+
+/// Evaluates the configured policies against scanned repositories.
+pub struct PolicyEngine {
+    branch_names: Vec<Regex>,
+    /// The subject pattern and how a mismatch is described.
+    commit_messages: Option<(Regex, String)>,
+    forbid_cloud_sync: bool,
+}
+
+impl PolicyEngine {
+    pub fn new(config: &PolicyConfig) -> io::Result<Self> {
+        let branch_names = config
+            .branch_names
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid branch name pattern `{}`: {}", pattern, err),
+                    )
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        let commit_messages = config
+            .commit_messages
+            .as_deref()
+            .map(|style| {
+                let (pattern, mismatch) = match style {
+                    "conventional" => (CONVENTIONAL_COMMIT, "is not a conventional commit".into()),
+                    pattern => (pattern, format!("does not match `{}`", pattern)),
+                };
+                let pattern = Regex::new(pattern).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid commit message pattern `{}`: {}", pattern, err),
+                    )
+                })?;
+                Ok::<_, io::Error>((pattern, mismatch))
+            })
+            .transpose()?;
+        Ok(PolicyEngine {
+            branch_names,
+            commit_messages,
+            forbid_cloud_sync: config.forbid_cloud_sync,
+        })
+    }
+
+    /// `timeout` bounds each git command that lists unpushed commits.
+    pub fn apply(&self, statuses: &mut [RepoStatus], timeout: Option<Duration>) {
+        for status in statuses {
+            if let Some(violation) = self.check_branch_name(status) {
+                status.policy_violations.push(violation);
+            }
+            let violations = self.check_commit_messages(status, timeout);
+            status.policy_violations.extend(violations);
+            if let Some(violation) = self.check_cloud_sync(status) {
+                status.policy_violations.push(violation);
+            }
+        }
+    }
+
+    fn check_cloud_sync(&self, status: &RepoStatus) -> Option<PolicyViolation> {
+        let service = status.sync_service.filter(|_| self.forbid_cloud_sync)?;
+        Some(PolicyViolation {
+            policy: "cloud-sync",
+            message: format!("repository is inside a {} folder", service),
+        })
+    }
+
+    /// The current branch must match at least one pattern; detached HEADs are exempt.
+    fn check_branch_name(&self, status: &RepoStatus) -> Option<PolicyViolation> {
+        self.branch_name_violation(status.branch.as_deref()?)
+    }
+
+    pub fn branch_name_violation(&self, branch: &str) -> Option<PolicyViolation> {
+        if self.branch_names.is_empty()
+            || self
+                .branch_names
+                .iter()
+                .any(|pattern| pattern.is_match(branch))
+        {
+            return None;
+        }
+        Some(PolicyViolation {
+            policy: "branch-name",
+            message: format!("branch `{}` matches no allowed pattern", branch),
+        })
+    }
+
+    pub fn commit_message_violation(&self, subject: &str) -> Option<PolicyViolation> {
+        let (pattern, mismatch) = self.commit_messages.as_ref()?;
+        if pattern.is_match(subject) {
+            return None;
+        }
+        Some(PolicyViolation {
+            policy: "commit-message",
+            message: format!("`{}` {}", subject, mismatch),
+        })
+    }
+
+    /// Lints the subjects of commits that are about to be pushed.
+    fn check_commit_messages(
+        &self,
+        status: &RepoStatus,
+        timeout: Option<Duration>,
+    ) -> Vec<PolicyViolation> {
+        // Only git history can be listed this way, and only on this machine: repos
+        // reached through `--docker`/`--ssh` carry a display path, not a local one.
+        if self.commit_messages.is_none()
+            || status.unpushed_commits == 0
+            || status.vcs != VcsKind::Git
+            || !status.path.is_dir()
+        {
+            return Vec::new();
+        }
+
+        let commits = match git::unpushed_subjects(&status.path, status.has_upstream, timeout) {
+            Ok(commits) => commits,
+            Err(err) => {
+                return vec![PolicyViolation {
+                    policy: "commit-message",
+                    message: format!("could not list unpushed commits: {}", err),
+                }];
+            }
+        };
+        commits
+            .into_iter()
+            .filter_map(|(commit, subject)| {
+                let violation = self.commit_message_violation(&subject)?;
+                Some(PolicyViolation {
+                    message: format!("{} {}", commit, violation.message),
+                    ..violation
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(commit_messages: &str) -> PolicyEngine {
+        PolicyEngine::new(&PolicyConfig {
+            commit_messages: Some(commit_messages.to_string()),
+            ..PolicyConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_conventional_commits() {
+        let engine = engine("conventional");
+        for subject in [
+            "feat: add --top",
+            "fix(scan): skip unreadable folders",
+            "refactor!: drop the v1 report",
+            "chore(deps)!: bump clap",
+        ] {
+            assert!(
+                engine.commit_message_violation(subject).is_none(),
+                "{}",
+                subject
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_subjects() {
+        let engine = engine("conventional");
+        for subject in [
+            "Add --top",
+            "feature: add --top",
+            "fix:add --top",
+            "fix(): add --top",
+            "FIX: add --top",
+            "wip",
+        ] {
+            let violation = engine.commit_message_violation(subject);
+            assert!(violation.is_some(), "{}", subject);
+            assert_eq!(violation.unwrap().policy, "commit-message");
+        }
+    }
+
+    #[test]
+    fn uses_custom_patterns() {
+        let engine = engine(r"^[A-Z]+-\d+ ");
+        assert!(engine.commit_message_violation("ABC-12 fix it").is_none());
+        assert!(engine.commit_message_violation("fix it").is_some());
+        assert!(
+            PolicyEngine::new(&PolicyConfig {
+                commit_messages: Some("(".to_string()),
+                ..PolicyConfig::default()
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+
+/// How often the spinner redraws with fresh counts.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Counters a running scan updates and other threads read while it runs.
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    dirs_walked: AtomicUsize,
+    repos_found: AtomicUsize,
+    repos_done: AtomicUsize,
+    is_walk_done: AtomicBool,
+    is_cancelled: AtomicBool,
+}
+
+/// A point-in-time copy of [`ScanProgress`].
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ProgressCounts {
+    pub dirs_walked: usize,
+    pub repos_found: usize,
+    pub repos_done: usize,
+    /// Every repo has been found; `repos_found` is final.
+    pub is_walk_done: bool,
+}
+
+impl ScanProgress {
+    pub fn walked_dir(&self) {
+        self.dirs_walked.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn found_repo(&self) {
+        self.repos_found.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn finished_repo(&self) {
+        self.repos_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn finish_walk(&self) {
+        self.is_walk_done.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops the scan before the next directory or repository; results so far are kept.
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn counts(&self) -> ProgressCounts {
+        ProgressCounts {
+            dirs_walked: self.dirs_walked.load(Ordering::Relaxed),
+            repos_found: self.repos_found.load(Ordering::Relaxed),
+            repos_done: self.repos_done.load(Ordering::Relaxed),
+            is_walk_done: self.is_walk_done.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A spinner on stderr that follows a [`ScanProgress`] until finished.
+pub struct ProgressDisplay {
+    bar: ProgressBar,
+    is_finished: Arc<AtomicBool>,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl ProgressDisplay {
+    pub fn start(progress: Arc<ScanProgress>) -> Self {
+        let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr());
+        bar.set_style(
+            ProgressStyle::with_template("{spinner} {elapsed} {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+        );
+        let is_finished = Arc::new(AtomicBool::new(false));
+        let ticker = {
+            let bar = bar.clone();
+            let is_finished = Arc::clone(&is_finished);
+            thread::spawn(move || {
+                while !is_finished.load(Ordering::Relaxed) {
+                    bar.set_message(describe(progress.counts()));
+                    bar.tick();
+                    thread::sleep(TICK);
+                }
+            })
+        };
+        ProgressDisplay {
+            bar,
+            is_finished,
+            ticker: Some(ticker),
+        }
+    }
+
+    /// Stops the spinner and erases it, so the report starts on a clean line.
+    pub fn finish(mut self) {
+        self.is_finished.store(true, Ordering::Relaxed);
+        if let Some(ticker) = self.ticker.take() {
+            let _ = ticker.join();
+        }
+        self.bar.finish_and_clear();
+    }
+}
+
+fn describe(counts: ProgressCounts) -> String {
+    let repos = if counts.is_walk_done {
+        format!("{}/{} repositories", counts.repos_done, counts.repos_found)
+    } else {
+        format!("{} repositories", counts.repos_done)
+    };
+    format!(
+        "walked {} directories, inspected {}",
+        counts.dirs_walked, repos
+    )
+}
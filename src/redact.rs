@@ -0,0 +1,302 @@
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::config_dir;
+use crate::{RepoStatus, ScanResult};
+
+/// File in the config directory holding the salt used when none is given.
+const SALT_FILE: &str = "redact-salt";
+
+/// Replaces identifying values with salted hashes that stay stable across runs.
+pub struct Redactor {
+    salt: String,
+}
+
+impl Redactor {
+    /// Without a salt, hashes of common paths and host names could be looked up in a
+    /// dictionary, so one is generated on first use and kept for later runs.
+    pub fn new(salt: Option<&str>) -> io::Result<Self> {
+        let salt = match salt {
+            Some(salt) if !salt.is_empty() => salt.to_string(),
+            _ => stored_salt()?,
+        };
+        Ok(Redactor { salt })
+    }
+
+    pub fn redact_scan(&self, scan: &mut ScanResult) {
+        for status in &mut scan.statuses {
+            self.redact_status(status);
+        }
+        for error in &mut scan.errors {
+            error.path = self.redact_path(&error.path);
+            error.message = "[redacted]".to_string();
+        }
+        for path in &mut scan.unscanned {
+            *path = self.redact_path(path);
+        }
+    }
+
+    pub fn redact_status(&self, status: &mut RepoStatus) {
+        status.path = self.redact_path(&status.path);
+        status.branch = status
+            .branch
+            .as_deref()
+            .map(|branch| self.redact_branch(branch));
+        if let Some(commit) = &mut status.last_commit {
+            commit.author = self.hash("author", &commit.author);
+            commit.subject = self.redact_subject(&commit.subject);
+        }
+        for name in &mut status.remotes {
+            *name = self.hash("remote", name);
+        }
+        for remote in &mut status.remote_ahead {
+            remote.remote = self.hash("remote", &remote.remote);
+        }
+        if let Some(remote) = &mut status.remote {
+            remote.remote_url = self.hash("url", &remote.remote_url);
+            remote.host = remote.host.as_deref().map(|host| self.hash("host", host));
+            remote.full_name = remote
+                .full_name
+                .as_deref()
+                .map(|name| self.hash("repo", name));
+        }
+        if let Some(request) = status
+            .enrichment
+            .as_mut()
+            .and_then(|enrichment| enrichment.open_request.as_mut())
+        {
+            request.url = self.hash("url", &request.url);
+        }
+        if let Some(volume) = &mut status.volume {
+            volume.mount_point = self.redact_path(&volume.mount_point);
+        }
+        for drift in &mut status.dependency_drift {
+            drift.name = self.hash("package", &drift.name);
+            drift.repo = self.redact_path(&drift.repo);
+        }
+        for file in &mut status.files {
+            file.path = self.redact_path(&file.path);
+        }
+        for violation in &mut status.policy_violations {
+            violation.message = "[redacted]".to_string();
+        }
+        for value in status.metadata.values_mut() {
+            *value = serde_json::Value::from("[redacted]");
+        }
+        for value in status
+            .findings
+            .values_mut()
+            .flat_map(|findings| findings.values_mut())
+        {
+            *value = serde_json::Value::from("[redacted]");
+        }
+        status.parent = status
+            .parent
+            .as_deref()
+            .map(|parent| self.redact_path(parent));
+    }
+
+    pub fn redact_path(&self, path: &std::path::Path) -> PathBuf {
+        PathBuf::from(self.hash("path", &path.to_string_lossy()))
+    }
+
+    /// The name of the machine that ran the scan.
+    pub fn redact_hostname(&self, host: &str) -> String {
+        self.hash("hostname", host)
+    }
+
+    pub fn redact_branch(&self, branch: &str) -> String {
+        self.hash("branch", branch)
+    }
+
+    pub fn redact_subject(&self, subject: &str) -> String {
+        self.hash("subject", subject)
+    }
+
+    /// A `--group-by` heading, which may name a host, an org or a directory.
+    pub fn redact_group(&self, group: &str) -> String {
+        self.hash("group", group)
+    }
+
+    /// The kind is mixed into the digest so equal strings in different fields don't correlate.
+    fn hash(&self, kind: &str, value: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update([0]);
+        hasher.update(kind.as_bytes());
+        hasher.update([0]);
+        hasher.update(value.as_bytes());
+        let digest = hasher.finalize();
+
+        let mut out = format!("{}-", kind);
+        for byte in &digest[..6] {
+            let _ = write!(out, "{:02x}", byte);
+        }
+        out
+    }
+}
+
+/// Reads the salt from the config directory, creating it there if it's missing.
+fn stored_salt() -> io::Result<String> {
+    let path = config_dir().map(|dir| dir.join(SALT_FILE)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "no config directory to keep a redaction salt in; pass --redact-salt",
+        )
+    })?;
+    match fs::read_to_string(&path) {
+        Ok(salt) if !salt.trim().is_empty() => return Ok(salt.trim().to_string()),
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(io::Error::new(
+                err.kind(),
+                format!("failed to read {}: {}", path.display(), err),
+            ));
+        }
+    }
+
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(io::Error::other)?;
+    let mut salt = String::new();
+    for byte in bytes {
+        let _ = write!(salt, "{:02x}", byte);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("failed to create {}: {}", path.display(), err),
+        )
+    })?;
+    writeln!(file, "{}", salt)?;
+    Ok(salt)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::vcs::{VcsKind, empty_status};
+    use crate::{ChangedFile, LastCommit, RemoteAhead, RemoteLocation, ScanError, ScanErrorKind};
+
+    fn redactor(salt: &str) -> Redactor {
+        Redactor::new(Some(salt)).unwrap()
+    }
+
+    #[test]
+    fn hashes_are_stable_for_a_salt() {
+        let path = Path::new("/home/alice/secret-project");
+        assert_eq!(
+            redactor("salt").redact_path(path),
+            PathBuf::from("path-16b773501e5d")
+        );
+        assert_eq!(
+            redactor("salt").redact_path(path),
+            redactor("salt").redact_path(path)
+        );
+        assert_ne!(
+            redactor("salt").redact_path(path),
+            redactor("pepper").redact_path(path)
+        );
+    }
+
+    #[test]
+    fn equal_values_in_different_fields_hash_differently() {
+        let redactor = redactor("salt");
+        let path = redactor.redact_path(Path::new("main"));
+        let branch = redactor.redact_branch("main");
+        assert_eq!(&branch[..7], "branch-");
+        assert_ne!(
+            path.to_string_lossy().trim_start_matches("path-"),
+            branch.trim_start_matches("branch-")
+        );
+    }
+
+    #[test]
+    fn redacts_every_identifying_field() {
+        let secrets = [
+            "/home/alice/work/acme-api",
+            "feature/acme-launch",
+            "Alice Example",
+            "Ship the acme launch",
+            "acme-mirror",
+            "git@git.acme.example:acme/api.git",
+            "git.acme.example",
+            "acme/api",
+            "src/acme_secrets.rs",
+            "/home/alice/work",
+            "/home/alice/broken",
+            "/home/alice/slow-disk",
+        ];
+        let status = RepoStatus {
+            branch: Some(secrets[1].to_string()),
+            last_commit: Some(LastCommit {
+                timestamp: 0,
+                author: secrets[2].to_string(),
+                subject: secrets[3].to_string(),
+            }),
+            remotes: vec![secrets[4].to_string()],
+            remote_ahead: vec![RemoteAhead {
+                remote: secrets[4].to_string(),
+                ahead: 1,
+                has_branch: true,
+            }],
+            remote: Some(RemoteLocation {
+                remote_url: secrets[5].to_string(),
+                host: Some(secrets[6].to_string()),
+                full_name: Some(secrets[7].to_string()),
+            }),
+            files: vec![ChangedFile {
+                code: " M".to_string(),
+                path: PathBuf::from(secrets[8]),
+            }],
+            parent: Some(PathBuf::from(secrets[9])),
+            ..empty_status(Path::new(secrets[0]), VcsKind::Git)
+        };
+        let mut scan = ScanResult {
+            statuses: vec![status],
+            errors: vec![ScanError {
+                path: PathBuf::from(secrets[10]),
+                kind: ScanErrorKind::Walk,
+                message: format!("cannot read {}", secrets[10]),
+            }],
+            unscanned: vec![PathBuf::from(secrets[11])],
+            sample: None,
+        };
+
+        redactor("salt").redact_scan(&mut scan);
+
+        let json = serde_json::to_string(&(&scan.statuses, &scan.errors, &scan.unscanned))
+            .unwrap()
+            .to_lowercase();
+        for secret in secrets {
+            assert!(!json.contains(&secret.to_lowercase()), "{} leaked", secret);
+        }
+        assert!(!json.contains("acme"), "{}", json);
+        let status = &scan.statuses[0];
+        assert_eq!(status.remotes[0], status.remote_ahead[0].remote);
+        assert!(status.path.to_string_lossy().starts_with("path-"));
+    }
+
+    #[test]
+    fn redacts_the_hostname() {
+        let host = redactor("salt").redact_hostname("alice-laptop");
+        assert!(host.starts_with("hostname-"));
+        assert!(!host.contains("alice"));
+    }
+}
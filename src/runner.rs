@@ -0,0 +1,138 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where git and discovery commands execute: locally, in a container or over ssh.
+#[derive(Clone, Debug)]
+pub enum Runner {
+    Local,
+    Docker { container: String },
+    Ssh { host: String },
+}
+
+/// A remote location to scan, parsed from `NAME[:ROOT]`.
+#[derive(Clone, Debug)]
+pub struct RemoteTarget {
+    pub runner: Runner,
+    pub root: PathBuf,
+}
+
+impl Runner {
+    /// Builds a command running `program` with `args` on this runner.
+    pub fn command<S: AsRef<OsStr>>(&self, program: &str, args: &[S]) -> Command {
+        match self {
+            Runner::Local => {
+                let mut command = Command::new(program);
+                command.args(args);
+                command
+            }
+            Runner::Docker { container } => {
+                let mut command = Command::new("docker");
+                command.arg("exec").arg(container).arg(program).args(args);
+                command
+            }
+            Runner::Ssh { host } => {
+                // ssh hands the remote shell a single command line, so quote every word.
+                let mut line = shell_quote(OsStr::new(program));
+                for arg in args {
+                    line.push(' ');
+                    line.push_str(&shell_quote(arg.as_ref()));
+                }
+                let mut command = Command::new("ssh");
+                // `--` keeps a host like `-oProxyCommand=...` from being read as an option.
+                command
+                    .arg("-o")
+                    .arg("BatchMode=yes")
+                    .arg("--")
+                    .arg(host)
+                    .arg(line);
+                command
+            }
+        }
+    }
+
+    /// How a path on this runner is shown in reports.
+    pub fn display_path(&self, path: &Path) -> PathBuf {
+        let path = path.strip_prefix(".").unwrap_or(path);
+        match self {
+            Runner::Local => path.to_path_buf(),
+            Runner::Docker { container } => {
+                let path = path.to_string_lossy();
+                let separator = if path.starts_with('/') { "" } else { "/" };
+                PathBuf::from(format!("docker://{}{}{}", container, separator, path))
+            }
+            Runner::Ssh { host } => PathBuf::from(format!("{}:{}", host, path.display())),
+        }
+    }
+}
+
+pub fn parse_docker_target(value: &str) -> Result<RemoteTarget, String> {
+    parse_target(value, |container| Runner::Docker { container })
+}
+
+pub fn parse_ssh_target(value: &str) -> Result<RemoteTarget, String> {
+    parse_target(value, |host| Runner::Ssh { host })
+}
+
+fn parse_target(value: &str, runner: impl Fn(String) -> Runner) -> Result<RemoteTarget, String> {
+    let (name, root) = value.split_once(':').unwrap_or((value, "."));
+    if name.is_empty() {
+        return Err(format!("missing name in `{}`", value));
+    }
+    // ssh and docker would take it for an option.
+    if name.starts_with('-') {
+        return Err(format!("name in `{}` must not start with `-`", value));
+    }
+    let root = if root.is_empty() { "." } else { root };
+    Ok(RemoteTarget {
+        runner: runner(name.to_string()),
+        root: PathBuf::from(root),
+    })
+}
+
+fn shell_quote(value: &OsStr) -> String {
+    let value = value.to_string_lossy();
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./,:=+@%~".contains(c));
+    if is_safe {
+        return value.into_owned();
+    }
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_name_and_root() {
+        let target = parse_ssh_target("build-box:/srv/src").unwrap();
+        assert!(matches!(target.runner, Runner::Ssh { ref host } if host == "build-box"));
+        assert_eq!(target.root, Path::new("/srv/src"));
+        assert_eq!(parse_docker_target("dev").unwrap().root, Path::new("."));
+        assert_eq!(parse_docker_target("dev:").unwrap().root, Path::new("."));
+    }
+
+    #[test]
+    fn rejects_names_that_look_like_options() {
+        assert!(parse_ssh_target("-oProxyCommand=touch /tmp/pwned").is_err());
+        assert!(parse_ssh_target("-oProxyCommand=x:/srv").is_err());
+        assert!(parse_docker_target("--privileged").is_err());
+        assert!(parse_ssh_target(":/srv").is_err());
+    }
+
+    #[test]
+    fn ends_ssh_options_before_the_host() {
+        let runner = Runner::Ssh {
+            host: "build-box".to_string(),
+        };
+        let command = runner.command("git", &["status"]);
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(
+            args,
+            ["-o", "BatchMode=yes", "--", "build-box", "git status"]
+        );
+    }
+}
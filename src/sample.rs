@@ -0,0 +1,166 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::{RepoStatus, Summary};
+
+/// z-score for a two-sided 95% confidence interval.
+const Z_95: f64 = 1.959_964;
+
+#[derive(Clone, Copy, Debug)]
+pub enum SampleSize {
+    Percent(f64),
+    Count(usize),
+}
+
+/// How many of the discovered repositories were actually inspected.
+#[derive(Clone, Copy, Debug)]
+pub struct SampleInfo {
+    pub discovered: usize,
+    pub sampled: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SampleReport {
+    pub discovered: usize,
+    pub sampled: usize,
+    pub confidence: f64,
+    pub estimates: Estimates,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Estimates {
+    pub dirty: Estimate,
+    pub repos_with_uncommitted: Estimate,
+    pub repos_with_unpushed: Estimate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Estimate {
+    pub estimate: usize,
+    pub low: usize,
+    pub high: usize,
+}
+
+/// Accepts a percentage (`10%`) or an absolute repository count (`50`).
+pub fn parse_sample_size(value: &str) -> Result<SampleSize, String> {
+    let value = value.trim();
+    if let Some(percent) = value.strip_suffix('%') {
+        let percent: f64 = percent
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid percentage `{}`", value))?;
+        if !(percent > 0.0 && percent <= 100.0) {
+            return Err(format!(
+                "sample percentage must be in (0, 100], got `{}`",
+                value
+            ));
+        }
+        return Ok(SampleSize::Percent(percent));
+    }
+
+    match value.parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!(
+            "invalid sample size `{}` (use e.g. `10%` or `50`)",
+            value
+        )),
+        Ok(count) => Ok(SampleSize::Count(count)),
+    }
+}
+
+/// Picks a uniformly random subset of the discovered repositories.
+pub fn choose<T: Ord>(mut repos: Vec<T>, size: SampleSize, seed: Option<u64>) -> Vec<T> {
+    let wanted = match size {
+        SampleSize::Percent(percent) => {
+            ((repos.len() as f64 * percent / 100.0).ceil() as usize).max(1)
+        }
+        SampleSize::Count(count) => count,
+    }
+    .min(repos.len());
+
+    let mut rng = XorShift::new(seed.unwrap_or_else(time_seed));
+    // Partial Fisher-Yates: the first `wanted` slots end up uniformly sampled.
+    for index in 0..wanted {
+        let pick = index + rng.below(repos.len() - index);
+        repos.swap(index, pick);
+    }
+    repos.truncate(wanted);
+    repos.sort();
+    repos
+}
+
+pub fn report(info: SampleInfo, statuses: &[RepoStatus], summary: &Summary) -> SampleReport {
+    let population = info.discovered;
+    let sampled = statuses.len();
+    SampleReport {
+        discovered: population,
+        sampled: info.sampled,
+        confidence: 0.95,
+        estimates: Estimates {
+            dirty: estimate(summary.dirty, sampled, population),
+            repos_with_uncommitted: estimate(summary.repos_with_uncommitted, sampled, population),
+            repos_with_unpushed: estimate(summary.repos_with_unpushed, sampled, population),
+        },
+    }
+}
+
+/// Wilson score interval with a finite population correction, clamped to what the
+/// sample already proves: at least `hits`, at most everything not seen to be a miss.
+fn estimate(hits: usize, sampled: usize, population: usize) -> Estimate {
+    if sampled == 0 {
+        return Estimate {
+            estimate: 0,
+            low: 0,
+            high: population,
+        };
+    }
+
+    let n = sampled as f64;
+    let p = hits as f64 / n;
+    let z2 = Z_95 * Z_95;
+    let denominator = 1.0 + z2 / n;
+    let center = (p + z2 / (2.0 * n)) / denominator;
+    let mut half_width = Z_95 / denominator * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    if population > 1 {
+        half_width *= ((population - sampled) as f64 / (population - 1) as f64).sqrt();
+    }
+
+    let total = population as f64;
+    let max_hits = population - (sampled - hits);
+    let point = (p * total).round() as usize;
+    let low = ((center - half_width).max(0.0) * total).floor() as usize;
+    let high = ((center + half_width).min(1.0) * total).ceil() as usize;
+    Estimate {
+        estimate: point,
+        low: low.clamp(hits, point),
+        high: high.clamp(point, max_hits),
+    }
+}
+
+fn time_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0);
+    nanos ^ u64::from(std::process::id()).rotate_left(32)
+}
+
+/// xorshift64*: plenty for picking a sample, and keeps `rand` out of the dependency tree.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        XorShift(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
@@ -0,0 +1,420 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use tracing::{debug, info, info_span, trace, warn};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::git::{self, get_repo_status_with, output_with_timeout};
+use crate::pool;
+use crate::progress::ScanProgress;
+use crate::runner::RemoteTarget;
+use crate::sample::{self, SampleInfo};
+use crate::vcs::{self, VcsKind};
+use crate::{NestedPolicy, RepoStatus, ScanError, ScanErrorKind, ScanOptions, ScanResult};
+
+/// Per-directory file in gitignore syntax that prunes matching paths from the walk.
+const IGNORE_FILE_NAME: &str = ".gittrackerignore";
+
+pub fn scan_root(root: &Path, options: &ScanOptions) -> ScanResult {
+    scan_root_with_progress(root, options, &ScanProgress::default())
+}
+
+/// Like [`scan_root`], reporting into `progress` and stopping early once it is cancelled.
+pub fn scan_root_with_progress(
+    root: &Path,
+    options: &ScanOptions,
+    progress: &ScanProgress,
+) -> ScanResult {
+    let _span = info_span!("scan", root = %root.display()).entered();
+    let started = Instant::now();
+    let mut scan = ScanResult::default();
+    let deadline = options.max_duration.map(|budget| Instant::now() + budget);
+    // Markers like `.git` sort first so a repo is known before any of its subfolders are
+    // walked, and a stable order lets `--max-duration` tell which folders were never reached.
+    let mut walker = WalkDir::new(root)
+        .follow_links(options.should_follow_symlinks)
+        .same_file_system(options.is_one_file_system)
+        .sort_by(markers_first);
+    if let Some(max_depth) = options.max_depth {
+        // The `.git` marker sits one level below the repository it belongs to.
+        walker = walker.max_depth(max_depth.saturating_add(1));
+    }
+    let mut walker = walker.into_iter();
+    let mut current_top: Option<PathBuf> = None;
+    let mut ignores: Vec<(usize, Gitignore)> = Vec::new();
+    // With `--sample`, repos are only collected during the walk and inspected afterwards.
+    let mut discovered = Vec::new();
+    // Repositories enclosing the current entry, with the depth of their root folder.
+    let mut repo_stack: Vec<(usize, PathBuf)> = Vec::new();
+    // With `--follow-symlinks`, directories already walked under another path.
+    let mut visited_dirs: HashSet<PathBuf> = HashSet::new();
+    let enabled = vcs::enabled(options);
+
+    while let Some(entry) = walker.next() {
+        if git::is_cancelled() || progress.is_cancelled() {
+            break;
+        }
+
+        let entry = match entry {
+            Ok(entry) => entry,
+            // A link back to one of its own ancestors; everything below was walked already.
+            Err(err) if err.loop_ancestor().is_some() => {
+                debug!(path = ?err.path(), "skipping symlink loop");
+                continue;
+            }
+            Err(err) => {
+                warn!(path = ?err.path(), error = %err, "failed to walk");
+                scan.errors.push(ScanError {
+                    path: err.path().unwrap_or(root).to_path_buf(),
+                    kind: ScanErrorKind::Walk,
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        // Matchers belong to ancestors of the entry; drop those of finished directories.
+        while ignores
+            .last()
+            .is_some_and(|(depth, _)| *depth >= entry.depth())
+        {
+            ignores.pop();
+        }
+        let is_dir = entry.file_type().is_dir();
+        if is_dir {
+            trace!(path = %entry.path().display(), "walking");
+            progress.walked_dir();
+        }
+        while repo_stack
+            .last()
+            .is_some_and(|(depth, _)| *depth >= entry.depth())
+        {
+            repo_stack.pop();
+        }
+        if options.should_follow_symlinks
+            && is_dir
+            && let Ok(canonical) = entry.path().canonicalize()
+            && !visited_dirs.insert(canonical)
+        {
+            debug!(path = %entry.path().display(), "skipping folder already walked through a link");
+            walker.skip_current_dir();
+            continue;
+        }
+        if options.nested == NestedPolicy::Skip && is_dir && !repo_stack.is_empty() {
+            walker.skip_current_dir();
+            continue;
+        }
+
+        if is_ignored(&ignores, entry.path(), is_dir) {
+            debug!(path = %entry.path().display(), "skipping ignored path");
+            if is_dir {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        if is_dir && let Some(matcher) = load_ignore_file(entry.path(), &mut scan.errors) {
+            ignores.push((entry.depth(), matcher));
+        }
+
+        if entry.depth() == 1 {
+            current_top = Some(entry.path().to_path_buf());
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            info!("--max-duration ran out");
+            let unvisited = unvisited_dirs(root, current_top.as_deref(), entry.depth());
+            scan.unscanned.extend(unvisited);
+            scan.unscanned.sort();
+            scan.unscanned.dedup();
+            break;
+        }
+
+        // Bare repos have no marker below them, so `--max-depth` applies to the folder itself.
+        let is_bare = is_dir
+            && enabled.contains(&VcsKind::Git)
+            && !vcs::is_marker_name(entry.file_name())
+            && options
+                .max_depth
+                .is_none_or(|max_depth| entry.depth() <= max_depth)
+            && git::is_bare_repo(entry.path());
+        let marked = if is_bare {
+            Some(VcsKind::Git)
+        } else if vcs::is_marker_name(entry.file_name()) {
+            entry
+                .path()
+                .parent()
+                .and_then(|repo_root| vcs::detect(repo_root, enabled))
+        } else {
+            None
+        };
+        let (repo_root, repo_depth) = if is_bare {
+            (entry.path().to_path_buf(), entry.depth())
+        } else {
+            let root = entry.path().parent().unwrap_or(entry.path());
+            (root.to_path_buf(), entry.depth().saturating_sub(1))
+        };
+        // A colocated repo's second marker names the same repo again.
+        let is_known = repo_stack
+            .last()
+            .is_some_and(|(depth, path)| *depth == repo_depth && *path == repo_root);
+        if let Some(kind) = marked.filter(|_| !is_known) {
+            let parent = repo_stack.last().map(|(_, path)| path.clone());
+            // Pushed even when not reported, so nested repos still learn their parent.
+            repo_stack.push((repo_depth, repo_root.clone()));
+
+            let is_reported = options.nested != NestedPolicy::ChildrenOnly || parent.is_some();
+            debug!(repo = %repo_root.display(), vcs = ?kind, is_reported, "found repository");
+            if is_reported && (options.sample.is_some() || options.should_fetch) {
+                discovered.push((repo_root, parent, kind));
+            } else if is_reported {
+                progress.found_repo();
+                status_repo(&mut scan, repo_root, parent, kind, options, deadline);
+                progress.finished_repo();
+            }
+        }
+
+        // A bare repo's folders are git internals, just like those of `.git`, `.jj` or `.hg`.
+        if is_bare || (is_dir && vcs::is_marker_name(entry.file_name())) {
+            walker.skip_current_dir();
+        }
+    }
+
+    if options.sample.is_some() || options.should_fetch {
+        let chosen = match options.sample {
+            Some(size) => {
+                let total = discovered.len();
+                let chosen = sample::choose(discovered, size, options.sample_seed);
+                scan.sample = Some(SampleInfo {
+                    discovered: total,
+                    sampled: chosen.len(),
+                });
+                chosen
+            }
+            None => discovered,
+        };
+        for _ in &chosen {
+            progress.found_repo();
+        }
+        progress.finish_walk();
+        if options.should_fetch {
+            let repos: Vec<&Path> = chosen
+                .iter()
+                .filter(|(_, _, kind)| *kind == VcsKind::Git)
+                .map(|(repo_root, _, _)| repo_root.as_path())
+                .collect();
+            fetch_all(&repos, options, deadline, &mut scan.errors);
+        }
+        for (repo_root, parent, kind) in chosen {
+            if git::is_cancelled() || progress.is_cancelled() {
+                break;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                scan.unscanned.push(repo_root);
+                continue;
+            }
+            status_repo(&mut scan, repo_root, parent, kind, options, deadline);
+            progress.finished_repo();
+        }
+    }
+    progress.finish_walk();
+
+    info!(
+        repos = scan.statuses.len(),
+        errors = scan.errors.len(),
+        unscanned = scan.unscanned.len(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "scan finished"
+    );
+    scan
+}
+
+/// Fetches every repo on a small pool of threads; a failed fetch is reported but the
+/// repo is still inspected with whatever it fetched last.
+fn fetch_all(
+    repos: &[&Path],
+    options: &ScanOptions,
+    deadline: Option<Instant>,
+    errors: &mut Vec<ScanError>,
+) {
+    let results = pool::map(repos, pool::default_workers(), |repo_root| {
+        if git::is_cancelled() || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(());
+        }
+        let timeout = remaining_budget(options.timeout, deadline);
+        git::fetch(repo_root, timeout, options.fetch_max_age)
+    });
+    errors.extend(results.into_iter().filter_map(Result::err));
+}
+
+fn status_repo(
+    scan: &mut ScanResult,
+    repo_root: PathBuf,
+    parent: Option<PathBuf>,
+    kind: VcsKind,
+    options: &ScanOptions,
+    deadline: Option<Instant>,
+) {
+    let repo_options = ScanOptions {
+        timeout: remaining_budget(options.timeout, deadline),
+        ..options.clone()
+    };
+    let started = Instant::now();
+    let result = kind.backend().status(&repo_root, &repo_options);
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(status) => {
+            debug!(
+                repo = %repo_root.display(),
+                is_dirty = status.is_dirty,
+                elapsed_ms,
+                "inspected repository"
+            );
+            scan.statuses.push(RepoStatus { parent, ..status });
+        }
+        Err(err) if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+            debug!(repo = %repo_root.display(), "--max-duration ran out during inspection");
+            scan.unscanned.push(err.path);
+        }
+        Err(err) => {
+            warn!(repo = %repo_root.display(), error = %err.message, elapsed_ms, "inspection failed");
+            scan.errors.push(err);
+        }
+    }
+}
+
+fn markers_first(a: &DirEntry, b: &DirEntry) -> Ordering {
+    (!vcs::is_marker_name(a.file_name()))
+        .cmp(&!vcs::is_marker_name(b.file_name()))
+        .then_with(|| a.file_name().cmp(b.file_name()))
+}
+
+/// The tighter of the per-repo timeout and whatever is left of the scan budget.
+fn remaining_budget(timeout: Option<Duration>, deadline: Option<Instant>) -> Option<Duration> {
+    let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+    match (timeout, remaining) {
+        (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+        (timeout, remaining) => timeout.or(remaining),
+    }
+}
+
+/// Top-level folders the walk had not finished when it stopped: the one in progress
+/// and every sibling sorted after it.
+fn unvisited_dirs(root: &Path, current_top: Option<&Path>, depth: usize) -> Vec<PathBuf> {
+    if depth == 0 {
+        return vec![root.to_path_buf()];
+    }
+
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return current_top.map(Path::to_path_buf).into_iter().collect();
+    };
+    let current_name = current_top.and_then(Path::file_name);
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter(|entry| current_name.is_none_or(|name| entry.file_name().as_os_str() >= name))
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Scans a container or ssh host. Discovery uses `find` on the target, so local-only
+/// features (`.gittrackerignore`, `--nested`, `--sample`) do not apply there.
+pub fn scan_remote(target: &RemoteTarget, options: &ScanOptions) -> ScanResult {
+    let mut scan = ScanResult::default();
+    let runner = &target.runner;
+    let mut args = vec![target.root.to_string_lossy().into_owned()];
+    if let Some(max_depth) = options.max_depth {
+        args.extend([
+            "-maxdepth".to_string(),
+            max_depth.saturating_add(1).to_string(),
+        ]);
+    }
+    args.extend(["-name", ".git", "-prune", "-print"].map(String::from));
+
+    let output = match output_with_timeout(runner.command("find", &args), None) {
+        Ok(output) => output,
+        Err(err) => {
+            scan.errors.push(ScanError {
+                path: runner.display_path(&target.root),
+                kind: ScanErrorKind::Walk,
+                message: format!("failed to list repositories: {}", err),
+            });
+            return scan;
+        }
+    };
+    // `find` exits non-zero on unreadable folders but still prints what it found.
+    if !output.status.success() {
+        scan.errors.push(ScanError {
+            path: runner.display_path(&target.root),
+            kind: ScanErrorKind::Walk,
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut markers: Vec<&str> = stdout.lines().collect();
+    markers.sort_unstable();
+    for marker in markers {
+        if git::is_cancelled() {
+            break;
+        }
+        let Some(repo_root) = Path::new(marker).parent() else {
+            continue;
+        };
+        match get_repo_status_with(runner, repo_root, options) {
+            Ok(status) => scan.statuses.push(RepoStatus {
+                path: runner.display_path(repo_root),
+                ..status
+            }),
+            Err(err) => scan.errors.push(ScanError {
+                path: runner.display_path(repo_root),
+                ..err
+            }),
+        }
+    }
+
+    scan
+}
+
+/// The deepest `.gittrackerignore` with an opinion wins, like nested `.gitignore` files.
+fn is_ignored(ignores: &[(usize, Gitignore)], path: &Path, is_dir: bool) -> bool {
+    for (_, matcher) in ignores.iter().rev() {
+        match matcher.matched(path, is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+    false
+}
+
+fn load_ignore_file(dir: &Path, errors: &mut Vec<ScanError>) -> Option<Gitignore> {
+    let path = dir.join(IGNORE_FILE_NAME);
+    if !path.is_file() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(err) = builder.add(&path) {
+        errors.push(ScanError {
+            path: path.clone(),
+            kind: ScanErrorKind::Walk,
+            message: err.to_string(),
+        });
+    }
+    match builder.build() {
+        Ok(matcher) => Some(matcher),
+        Err(err) => {
+            errors.push(ScanError {
+                path,
+                kind: ScanErrorKind::Walk,
+                message: err.to_string(),
+            });
+            None
+        }
+    }
+}
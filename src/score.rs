@@ -0,0 +1,98 @@
+use crate::config::ScoreConfig;
+use crate::{RepoStatus, SECONDS_PER_DAY, unix_now};
+
+/// Sets each repo's `score`: the weighted sum of its uncommitted files, unpushed
+/// commits, stashes, idle days and a missing upstream. Idle days only count while
+/// there is something to lose, so a clean repo nobody touched in a year scores 0.
+pub fn apply(weights: &ScoreConfig, statuses: &mut [RepoStatus]) {
+    let now = unix_now();
+    for status in statuses {
+        let has_local_work = status.is_dirty || status.stashes > 0;
+        let idle_days = match status.last_touched {
+            Some(touched) if has_local_work => {
+                now.saturating_sub(touched) as f64 / SECONDS_PER_DAY as f64
+            }
+            _ => 0.0,
+        };
+        let score = weights.uncommitted * status.uncommitted_changes as f64
+            + weights.unpushed * status.unpushed_commits as f64
+            + weights.stashes * status.stashes as f64
+            + weights.stale_days * idle_days
+            + if status.has_upstream {
+                0.0
+            } else {
+                weights.no_upstream
+            };
+        // One decimal is plenty for ranking and keeps JSON readable.
+        status.score = (score * 10.0).round() / 10.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::vcs::{VcsKind, empty_status};
+
+    fn status() -> RepoStatus {
+        RepoStatus {
+            has_upstream: true,
+            ..empty_status(Path::new("repo"), VcsKind::Git)
+        }
+    }
+
+    fn score(status: RepoStatus) -> f64 {
+        let mut statuses = [status];
+        apply(&ScoreConfig::default(), &mut statuses);
+        statuses[0].score
+    }
+
+    #[test]
+    fn spotless_repo_scores_zero() {
+        assert_eq!(score(status()), 0.0);
+    }
+
+    #[test]
+    fn weighs_each_factor() {
+        let status = RepoStatus {
+            is_dirty: true,
+            uncommitted_changes: 3,
+            unpushed_commits: 2,
+            stashes: 1,
+            has_upstream: false,
+            ..status()
+        };
+        assert_eq!(score(status), 3.0 + 4.0 + 1.0 + 5.0);
+    }
+
+    #[test]
+    fn idle_days_count_only_with_local_work() {
+        let ten_days_ago = unix_now() - 10 * SECONDS_PER_DAY;
+        let clean = RepoStatus {
+            last_touched: Some(ten_days_ago),
+            ..status()
+        };
+        assert_eq!(score(clean.clone()), 0.0);
+
+        let stashed = RepoStatus {
+            stashes: 1,
+            ..clean
+        };
+        assert_eq!(score(stashed), 1.0 + 1.0);
+    }
+
+    #[test]
+    fn uses_configured_weights() {
+        let weights = ScoreConfig {
+            uncommitted: 0.5,
+            ..ScoreConfig::default()
+        };
+        let mut statuses = [RepoStatus {
+            uncommitted_changes: 3,
+            ..status()
+        }];
+        apply(&weights, &mut statuses);
+        assert_eq!(statuses[0].score, 1.5);
+    }
+}
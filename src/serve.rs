@@ -0,0 +1,533 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::config::Config;
+use crate::notify::Notifier;
+use crate::policy::PolicyEngine;
+use crate::progress::{ProgressCounts, ScanProgress};
+use crate::redact::Redactor;
+use crate::scan::scan_root_with_progress;
+use crate::{RepoStatus, ScanError, ScanOptions, ServeArgs, apply_config};
+
+const TEXT_PLAIN: &str = "text/plain; charset=utf-8";
+const APPLICATION_JSON: &str = "application/json";
+const PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// Finished scans kept around so clients polling `/scan/{id}` see how they ended.
+const FINISHED_SCANS_KEPT: usize = 16;
+
+/// Results of the most recent scan, shared between the scanner and the HTTP threads.
+#[derive(Default)]
+struct Snapshot {
+    statuses: Vec<RepoStatus>,
+    errors: Vec<ScanError>,
+    scanned_at: u64,
+    duration_secs: f64,
+}
+
+struct State {
+    root: PathBuf,
+    options: ScanOptions,
+    config: Config,
+    policies: PolicyEngine,
+    redactor: Option<Redactor>,
+    notifier: Option<Mutex<Notifier>>,
+    snapshot: RwLock<Snapshot>,
+    /// Serializes scans so a `POST /rescan` never overlaps the periodic scan.
+    scan_lock: Mutex<()>,
+    /// Scans started through `POST /rescan`, by id.
+    jobs: Mutex<BTreeMap<u64, Arc<ScanJob>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum JobState {
+    /// Waiting for a scan already in progress.
+    Queued,
+    Running,
+    Finished,
+    Cancelled,
+}
+
+struct ScanJob {
+    id: u64,
+    created: Instant,
+    state: Mutex<JobState>,
+    progress: ScanProgress,
+    /// Repos in the previous scan, the best guess at the total while the walk runs.
+    expected_repos: usize,
+}
+
+#[derive(Serialize)]
+struct ApiScan {
+    id: u64,
+    state: JobState,
+    #[serde(flatten)]
+    progress: ProgressCounts,
+    elapsed_secs: f64,
+    /// Extrapolated from the pace so far; absent until a repo has been inspected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eta_secs: Option<f64>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Routes {
+    should_serve_api: bool,
+    should_serve_metrics: bool,
+}
+
+#[derive(Serialize)]
+struct ApiRepo<'a> {
+    id: String,
+    #[serde(flatten)]
+    status: &'a RepoStatus,
+}
+
+#[derive(Serialize)]
+struct ApiRepoList<'a> {
+    total: usize,
+    scanned_at: u64,
+    repos: Vec<ApiRepo<'a>>,
+    errors: &'a [ScanError],
+}
+
+pub fn run(
+    args: &ServeArgs,
+    options: ScanOptions,
+    config: Config,
+    policies: PolicyEngine,
+    redactor: Option<Redactor>,
+) -> io::Result<()> {
+    let mut endpoints: Vec<(SocketAddr, Routes)> = Vec::new();
+    if let Some(listen) = &args.listen {
+        let addr = parse_listen_addr(listen)?;
+        endpoint_routes(&mut endpoints, addr).should_serve_api = true;
+    }
+    if let Some(metrics) = &args.metrics {
+        let addr = parse_listen_addr(metrics)?;
+        endpoint_routes(&mut endpoints, addr).should_serve_metrics = true;
+    }
+
+    let state = Arc::new(State {
+        root: args.root.clone(),
+        options,
+        config,
+        policies,
+        redactor,
+        notifier: args
+            .notify
+            .then(|| Mutex::new(Notifier::new(args.notify_stale_after))),
+        snapshot: RwLock::new(Snapshot::default()),
+        scan_lock: Mutex::new(()),
+        jobs: Mutex::new(BTreeMap::new()),
+    });
+    rescan(&state, &ScanProgress::default());
+
+    if !args.interval.is_zero() {
+        let state = Arc::clone(&state);
+        let interval = args.interval;
+        thread::spawn(move || {
+            loop {
+                thread::sleep(interval);
+                rescan(&state, &ScanProgress::default());
+            }
+        });
+    }
+
+    let mut handles = Vec::new();
+    for (addr, routes) in endpoints {
+        let server = Server::http(addr).map_err(io::Error::other)?;
+        eprintln!("listening on http://{}", addr);
+        let state = Arc::clone(&state);
+        handles.push(thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(request, &state, routes);
+            }
+        }));
+    }
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    Ok(())
+}
+
+fn endpoint_routes(endpoints: &mut Vec<(SocketAddr, Routes)>, addr: SocketAddr) -> &mut Routes {
+    let index = match endpoints.iter().position(|(existing, _)| *existing == addr) {
+        Some(index) => index,
+        None => {
+            endpoints.push((addr, Routes::default()));
+            endpoints.len() - 1
+        }
+    };
+    &mut endpoints[index].1
+}
+
+/// Scans and publishes the result; a cancelled scan leaves the last snapshot in place.
+fn rescan(state: &State, progress: &ScanProgress) -> bool {
+    let _guard = state.scan_lock.lock();
+    if progress.is_cancelled() {
+        return false;
+    }
+    let started = Instant::now();
+    let mut scan = scan_root_with_progress(&state.root, &state.options, progress);
+    if progress.is_cancelled() {
+        return false;
+    }
+    apply_config(
+        &mut scan.statuses,
+        &state.config,
+        &state.policies,
+        &state.options,
+        true,
+    );
+    // Notifications stay on this machine and need the real paths; only the API is redacted.
+    if let Some(Ok(mut notifier)) = state.notifier.as_ref().map(Mutex::lock) {
+        notifier.observe(&scan.statuses);
+    }
+    if let Some(redactor) = &state.redactor {
+        redactor.redact_scan(&mut scan);
+    }
+    let scanned_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+
+    if let Ok(mut snapshot) = state.snapshot.write() {
+        *snapshot = Snapshot {
+            statuses: scan.statuses,
+            errors: scan.errors,
+            scanned_at,
+            duration_secs: started.elapsed().as_secs_f64(),
+        };
+    }
+    true
+}
+
+/// Starts a scan in the background and returns its job for `/scan/{id}`.
+fn start_scan(state: &Arc<State>) -> Arc<ScanJob> {
+    let expected_repos = state
+        .snapshot
+        .read()
+        .map(|snapshot| snapshot.statuses.len() + snapshot.errors.len())
+        .unwrap_or(0);
+    let job = {
+        let mut jobs = state.jobs.lock().unwrap_or_else(|err| err.into_inner());
+        let id = jobs.keys().next_back().map_or(1, |last| last + 1);
+        let job = Arc::new(ScanJob {
+            id,
+            created: Instant::now(),
+            state: Mutex::new(JobState::Queued),
+            progress: ScanProgress::default(),
+            expected_repos,
+        });
+        jobs.insert(id, Arc::clone(&job));
+        prune_jobs(&mut jobs);
+        job
+    };
+
+    let state = Arc::clone(state);
+    let background_job = Arc::clone(&job);
+    thread::spawn(move || {
+        let job = background_job;
+        job.set_state(JobState::Running);
+        let is_complete = rescan(&state, &job.progress);
+        job.set_state(if is_complete {
+            JobState::Finished
+        } else {
+            JobState::Cancelled
+        });
+    });
+    job
+}
+
+/// Drops the oldest finished or cancelled jobs beyond [`FINISHED_SCANS_KEPT`].
+fn prune_jobs(jobs: &mut BTreeMap<u64, Arc<ScanJob>>) {
+    let done: Vec<u64> = jobs
+        .iter()
+        .filter(|(_, job)| matches!(job.state(), JobState::Finished | JobState::Cancelled))
+        .map(|(id, _)| *id)
+        .collect();
+    for id in done
+        .iter()
+        .take(done.len().saturating_sub(FINISHED_SCANS_KEPT))
+    {
+        jobs.remove(id);
+    }
+}
+
+impl ScanJob {
+    fn state(&self) -> JobState {
+        *self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn set_state(&self, new_state: JobState) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        // A cancel that lands while the scan starts must not be overwritten.
+        if *state != JobState::Cancelled {
+            *state = new_state;
+        }
+    }
+
+    fn cancel(&self) {
+        self.progress.cancel();
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if *state == JobState::Queued {
+            *state = JobState::Cancelled;
+        }
+    }
+
+    fn to_api(&self) -> ApiScan {
+        let progress = self.progress.counts();
+        let state = self.state();
+        let elapsed_secs = self.created.elapsed().as_secs_f64();
+        let total = if progress.is_walk_done {
+            progress.repos_found
+        } else {
+            progress.repos_found.max(self.expected_repos)
+        };
+        let eta_secs = (state == JobState::Running && progress.repos_done > 0).then(|| {
+            let per_repo = elapsed_secs / progress.repos_done as f64;
+            per_repo * total.saturating_sub(progress.repos_done) as f64
+        });
+        ApiScan {
+            id: self.id,
+            state,
+            progress,
+            elapsed_secs,
+            eta_secs,
+        }
+    }
+}
+
+fn handle_request(request: Request, state: &Arc<State>, routes: Routes) {
+    let method = request.method().clone();
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+
+    let (status_code, content_type, body) = match (method, path.as_str()) {
+        (Method::Get, "/metrics") if routes.should_serve_metrics => {
+            with_snapshot(state, |snapshot| {
+                (PROMETHEUS_TEXT, render_metrics(snapshot))
+            })
+        }
+        (Method::Get, "/repos") if routes.should_serve_api => with_snapshot(state, |snapshot| {
+            (APPLICATION_JSON, render_repo_list(snapshot))
+        }),
+        (Method::Post, "/rescan") if routes.should_serve_api => {
+            let job = start_scan(state);
+            (202, APPLICATION_JSON, render_scan(&job))
+        }
+        (method @ (Method::Get | Method::Delete), route)
+            if routes.should_serve_api && route.starts_with("/scan/") =>
+        {
+            let job = route["/scan/".len()..].parse::<u64>().ok().and_then(|id| {
+                let jobs = state.jobs.lock().unwrap_or_else(|err| err.into_inner());
+                jobs.get(&id).cloned()
+            });
+            match job {
+                Some(job) => {
+                    if method == Method::Delete {
+                        job.cancel();
+                    }
+                    (200, APPLICATION_JSON, render_scan(&job))
+                }
+                None => (404, TEXT_PLAIN, "scan not found\n".to_string()),
+            }
+        }
+        (Method::Get, route) if routes.should_serve_api && route.starts_with("/repos/") => {
+            let id = &route["/repos/".len()..];
+            match state.snapshot.read() {
+                Ok(snapshot) => match snapshot
+                    .statuses
+                    .iter()
+                    .find(|status| repo_id(status) == id)
+                {
+                    Some(status) => (200, APPLICATION_JSON, render_repo(status)),
+                    None => (404, TEXT_PLAIN, "repository not found\n".to_string()),
+                },
+                Err(_) => (500, TEXT_PLAIN, "snapshot unavailable\n".to_string()),
+            }
+        }
+        _ => (404, TEXT_PLAIN, "not found\n".to_string()),
+    };
+
+    let mut response = Response::from_string(body).with_status_code(status_code);
+    if let Ok(header) = Header::from_bytes("Content-Type", content_type) {
+        response.add_header(header);
+    }
+    let _ = request.respond(response);
+}
+
+fn with_snapshot(
+    state: &State,
+    render: impl FnOnce(&Snapshot) -> (&'static str, String),
+) -> (u16, &'static str, String) {
+    match state.snapshot.read() {
+        Ok(snapshot) => {
+            let (content_type, body) = render(&snapshot);
+            (200, content_type, body)
+        }
+        Err(_) => (500, TEXT_PLAIN, "snapshot unavailable\n".to_string()),
+    }
+}
+
+/// Stable identifier for a repository: FNV-1a of its path, so ids survive rescans.
+fn repo_id(status: &RepoStatus) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in status.path.to_string_lossy().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn render_repo_list(snapshot: &Snapshot) -> String {
+    let output = ApiRepoList {
+        total: snapshot.statuses.len(),
+        scanned_at: snapshot.scanned_at,
+        repos: snapshot
+            .statuses
+            .iter()
+            .map(|status| ApiRepo {
+                id: repo_id(status),
+                status,
+            })
+            .collect(),
+        errors: &snapshot.errors,
+    };
+    serde_json::to_string_pretty(&output).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn render_scan(job: &ScanJob) -> String {
+    serde_json::to_string_pretty(&job.to_api()).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn render_repo(status: &RepoStatus) -> String {
+    let output = ApiRepo {
+        id: repo_id(status),
+        status,
+    };
+    serde_json::to_string_pretty(&output).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn render_metrics(snapshot: &Snapshot) -> String {
+    let statuses = &snapshot.statuses;
+    let dirty = statuses.iter().filter(|status| status.is_dirty).count();
+    let mut out = String::new();
+
+    write_gauge(
+        &mut out,
+        "gittracker_repos",
+        "Number of repositories found in the last scan.",
+        statuses.len(),
+    );
+    write_gauge(
+        &mut out,
+        "gittracker_dirty_repos",
+        "Number of repositories with uncommitted changes or unpushed commits.",
+        dirty,
+    );
+    write_gauge(
+        &mut out,
+        "gittracker_scan_errors",
+        "Directories or repositories that could not be scanned in the last scan.",
+        snapshot.errors.len(),
+    );
+    write_gauge(
+        &mut out,
+        "gittracker_last_scan_timestamp_seconds",
+        "Unix time at which the last scan finished.",
+        snapshot.scanned_at,
+    );
+    write_gauge(
+        &mut out,
+        "gittracker_scan_duration_seconds",
+        "Wall-clock duration of the last scan.",
+        snapshot.duration_secs,
+    );
+
+    write_repo_gauge(
+        &mut out,
+        "gittracker_uncommitted_changes",
+        "Uncommitted files per repository.",
+        statuses,
+        |status| status.uncommitted_changes,
+    );
+    write_repo_gauge(
+        &mut out,
+        "gittracker_conflicts",
+        "Unmerged paths per repository.",
+        statuses,
+        |status| status.conflicts,
+    );
+    write_repo_gauge(
+        &mut out,
+        "gittracker_unpushed_commits",
+        "Commits ahead of upstream per repository.",
+        statuses,
+        |status| status.unpushed_commits,
+    );
+    write_repo_gauge(
+        &mut out,
+        "gittracker_has_upstream",
+        "Whether the current branch tracks an upstream (1) or not (0).",
+        statuses,
+        |status| usize::from(status.has_upstream),
+    );
+
+    out
+}
+
+fn write_gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn write_repo_gauge(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    statuses: &[RepoStatus],
+    value: impl Fn(&RepoStatus) -> usize,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for status in statuses {
+        let repo = escape_label(&status.path.to_string_lossy());
+        let _ = writeln!(out, "{}{{repo=\"{}\"}} {}", name, repo, value(status));
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Accepts `host:port` or a bare `:port`, which binds on localhost only; the API lists
+/// every repo path, so other machines need an explicit address such as `0.0.0.0:9185`.
+fn parse_listen_addr(value: &str) -> io::Result<SocketAddr> {
+    let value = if value.starts_with(':') {
+        format!("127.0.0.1{}", value)
+    } else {
+        value.to_string()
+    };
+
+    value.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid listen address `{}`", value),
+        )
+    })
+}
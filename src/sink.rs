@@ -0,0 +1,292 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::color::{self, ColorChoice, Theme};
+use crate::config::{WebhookConfig, WebhookFormat};
+use crate::git::output_with_input;
+use crate::redact::Redactor;
+use crate::sample::SampleReport;
+use crate::trend::Trend;
+use crate::{
+    RepoStatus, ScanError, Summary, TopList, render_human, render_json, render_paths, webhook,
+};
+
+/// Everything a sink may need from one scan.
+pub struct Report<'a> {
+    pub statuses: &'a [RepoStatus],
+    pub errors: &'a [ScanError],
+    pub unscanned: &'a [PathBuf],
+    pub summary: &'a Summary,
+    pub sample: Option<&'a SampleReport>,
+    pub top: Option<&'a TopList<'a>>,
+    /// `--group-by` heading per repo, in the same order as `statuses`.
+    pub groups: Option<&'a [String]>,
+    pub show_clean: bool,
+    pub should_show_idle: bool,
+    /// `--summary`: totals instead of per-repo lines.
+    pub is_summary_only: bool,
+    /// `-0`: the paths report ends each path with NUL instead of a newline.
+    pub is_nul_terminated: bool,
+    /// How the totals changed since an earlier scan of the same root.
+    pub trend: Option<&'a Trend>,
+    /// Set with `--redact`, for sinks that add identifying values of their own.
+    pub redactor: Option<&'a Redactor>,
+}
+
+/// A destination for the scan report; every configured sink sees the same report.
+pub trait OutputSink {
+    fn emit(&self, report: &Report) -> io::Result<()>;
+}
+
+/// A sink as written on the command line or in `config.toml`: `human`, `json`,
+/// `paths`, the same with `:FILE`, or `webhook:URL`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum SinkSpec {
+    Human(Option<PathBuf>),
+    Json(Option<PathBuf>),
+    /// Only the listed repository paths, one per line.
+    Paths(Option<PathBuf>),
+    Webhook(String),
+}
+
+impl SinkSpec {
+    pub fn is_json(&self) -> bool {
+        matches!(self, SinkSpec::Json(_))
+    }
+
+    pub fn is_stdout(&self) -> bool {
+        !matches!(self, SinkSpec::Webhook(_)) && self.path().is_none()
+    }
+
+    fn path(&self) -> Option<&Path> {
+        match self {
+            SinkSpec::Human(path) | SinkSpec::Json(path) | SinkSpec::Paths(path) => path.as_deref(),
+            SinkSpec::Webhook(_) => None,
+        }
+    }
+}
+
+impl FromStr for SinkSpec {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        // `json:` with nothing after it still means stdout.
+        let (kind, target) = match value.split_once(':') {
+            Some((kind, target)) => (kind, Some(target).filter(|target| !target.is_empty())),
+            None => (value, None),
+        };
+        match (kind, target) {
+            ("human", target) => Ok(SinkSpec::Human(target.map(PathBuf::from))),
+            ("json", target) => Ok(SinkSpec::Json(target.map(PathBuf::from))),
+            ("paths", target) => Ok(SinkSpec::Paths(target.map(PathBuf::from))),
+            ("webhook", Some(url)) => Ok(SinkSpec::Webhook(url.to_string())),
+            ("webhook", _) => Err("a webhook sink needs a URL, as `webhook:URL`".to_string()),
+            _ => Err(format!(
+                "unknown sink `{}`; expected human, json, paths or webhook",
+                kind
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for SinkSpec {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ReportFormat {
+    Human,
+    Json,
+    Paths,
+}
+
+/// The human, JSON or paths report, written to stdout or a file.
+struct ReportSink {
+    format: ReportFormat,
+    path: Option<PathBuf>,
+    /// age recipients the file is encrypted to.
+    recipients: Vec<String>,
+    /// Set when the human report is colored.
+    theme: Option<Theme>,
+}
+
+impl OutputSink for ReportSink {
+    fn emit(&self, report: &Report) -> io::Result<()> {
+        let rendered = match self.format {
+            ReportFormat::Human => render_human(report, self.theme.as_ref()),
+            ReportFormat::Json => render_json(report),
+            ReportFormat::Paths => render_paths(report),
+        };
+        debug!(format = ?self.format, path = ?self.path, bytes = rendered.len(), "writing report");
+        write_report(&rendered, self.path.as_deref(), &self.recipients)
+    }
+}
+
+struct WebhookSink {
+    webhooks: Vec<WebhookConfig>,
+}
+
+impl OutputSink for WebhookSink {
+    /// Delivery failures are warnings, so a flaky endpoint never fails the scan.
+    fn emit(&self, report: &Report) -> io::Result<()> {
+        debug!(webhooks = self.webhooks.len(), "sending report");
+        webhook::send_all(
+            &self.webhooks,
+            report.statuses,
+            report.summary,
+            report.redactor,
+        );
+        Ok(())
+    }
+}
+
+/// One sink per spec, plus one for `configured_webhooks`. `recipients` apply to
+/// every sink writing a file.
+pub fn build(
+    specs: &[SinkSpec],
+    recipients: &[String],
+    configured_webhooks: &[WebhookConfig],
+    color: ColorChoice,
+    theme: &Theme,
+) -> io::Result<Vec<Box<dyn OutputSink>>> {
+    if !recipients.is_empty() && specs.iter().all(|spec| spec.path().is_none()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--encrypt-to needs a report written to a file",
+        ));
+    }
+
+    let mut sinks: Vec<Box<dyn OutputSink>> = Vec::new();
+    let mut webhooks = configured_webhooks.to_vec();
+    for spec in specs {
+        match spec {
+            SinkSpec::Human(path) | SinkSpec::Json(path) | SinkSpec::Paths(path) => {
+                let format = match spec {
+                    SinkSpec::Json(_) => ReportFormat::Json,
+                    SinkSpec::Paths(_) => ReportFormat::Paths,
+                    _ => ReportFormat::Human,
+                };
+                sinks.push(Box::new(ReportSink {
+                    format,
+                    path: path.clone(),
+                    recipients: recipients.to_vec(),
+                    theme: color::is_enabled(color, path.is_none()).then(|| theme.clone()),
+                }))
+            }
+            SinkSpec::Webhook(url) => webhooks.push(WebhookConfig {
+                url: url.clone(),
+                format: WebhookFormat::Json,
+                min_dirty: None,
+                min_unpushed: None,
+            }),
+        }
+    }
+    if !webhooks.is_empty() {
+        sinks.push(Box::new(WebhookSink { webhooks }));
+    }
+    Ok(sinks)
+}
+
+fn write_report(report: &str, output: Option<&Path>, recipients: &[String]) -> io::Result<()> {
+    let Some(path) = output else {
+        io::stdout().write_all(report.as_bytes())?;
+        return Ok(());
+    };
+
+    if recipients.is_empty() {
+        return std::fs::write(path, report);
+    }
+
+    encrypt_to_file(report, path, recipients)
+}
+
+/// Pipes the report through `age` (or `rage`) so plaintext never touches disk.
+fn encrypt_to_file(report: &str, path: &Path, recipients: &[String]) -> io::Result<()> {
+    for program in ["age", "rage"] {
+        let mut command = Command::new(program);
+        for recipient in recipients {
+            command.arg("-r").arg(recipient);
+        }
+        command.arg("-o").arg(path);
+
+        // Feeds stdin from a thread while draining stderr, so a chatty encryptor can't
+        // fill its stderr pipe and stall both sides.
+        let output = match output_with_input(command, Some(report.as_bytes().to_vec()), None) {
+            Ok(output) => output,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            stderr.trim()
+        )));
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "neither `age` nor `rage` was found in PATH",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_report_sinks() {
+        assert_eq!("human".parse(), Ok(SinkSpec::Human(None)));
+        assert_eq!("json".parse(), Ok(SinkSpec::Json(None)));
+        assert_eq!("json:".parse(), Ok(SinkSpec::Json(None)));
+        assert_eq!(
+            "json:out/report.json".parse(),
+            Ok(SinkSpec::Json(Some(PathBuf::from("out/report.json"))))
+        );
+        assert_eq!(
+            "paths:C:/repos.txt".parse(),
+            Ok(SinkSpec::Paths(Some(PathBuf::from("C:/repos.txt"))))
+        );
+    }
+
+    #[test]
+    fn parses_webhook_sinks() {
+        assert_eq!(
+            "webhook:https://hooks.example.com:8443/x".parse(),
+            Ok(SinkSpec::Webhook(
+                "https://hooks.example.com:8443/x".to_string()
+            ))
+        );
+        assert!("webhook".parse::<SinkSpec>().is_err());
+        assert!("webhook:".parse::<SinkSpec>().is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_sinks() {
+        assert!("xml".parse::<SinkSpec>().is_err());
+        assert!("xml:report.xml".parse::<SinkSpec>().is_err());
+        assert!("".parse::<SinkSpec>().is_err());
+    }
+
+    #[test]
+    fn only_report_sinks_without_a_file_write_to_stdout() {
+        assert!(SinkSpec::Human(None).is_stdout());
+        assert!(!SinkSpec::Json(Some(PathBuf::from("r.json"))).is_stdout());
+        assert!(!SinkSpec::Webhook("https://x".to_string()).is_stdout());
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::RepoStatus;
+use crate::git::{is_bare_repo, run_git};
+
+/// `gc.auto` default: `git gc --auto` repacks past this many loose objects.
+const GC_LOOSE_OBJECTS: usize = 6700;
+/// `gc.autoPackLimit` default: `git gc --auto` consolidates past this many packs.
+const GC_PACKS: usize = 50;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RepoSizes {
+    /// Checked-out files, excluding `.git` and nested repositories.
+    pub worktree_bytes: u64,
+    pub git_dir_bytes: u64,
+    pub loose_objects: usize,
+    pub packs: usize,
+    /// Past the thresholds at which `git gc --auto` would act.
+    pub should_gc: bool,
+}
+
+impl RepoSizes {
+    pub fn total_bytes(&self) -> u64 {
+        self.worktree_bytes + self.git_dir_bytes
+    }
+}
+
+/// Measures every local repo; repos reached through `--docker`/`--ssh` are skipped.
+pub fn measure(statuses: &mut [RepoStatus]) {
+    for status in statuses {
+        if !status.path.is_dir() {
+            continue;
+        }
+        status.sizes = measure_repo(&status.path, status.is_bare);
+    }
+}
+
+fn measure_repo(repo: &Path, is_bare: bool) -> Option<RepoSizes> {
+    let git_dir = git_dir(repo)?;
+    let counts = run_git(repo, &["count-objects", "-v"]).ok()?;
+    let count = |key: &str| {
+        counts
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .find(|(name, _)| *name == key)
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0)
+    };
+    let loose_objects = count("count");
+    let packs = count("packs");
+
+    Some(RepoSizes {
+        worktree_bytes: if is_bare { 0 } else { worktree_bytes(repo) },
+        git_dir_bytes: dir_bytes(&git_dir),
+        loose_objects,
+        packs,
+        should_gc: loose_objects > GC_LOOSE_OBJECTS || packs > GC_PACKS,
+    })
+}
+
+fn git_dir(repo: &Path) -> Option<PathBuf> {
+    let dot_git = repo.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    if is_bare_repo(repo) {
+        return Some(repo.to_path_buf());
+    }
+    // Worktrees and submodules point at their git dir through a `.git` file.
+    let git_dir = run_git(repo, &["rev-parse", "--git-dir"]).ok()?;
+    Some(repo.join(git_dir.trim()))
+}
+
+fn worktree_bytes(repo: &Path) -> u64 {
+    WalkDir::new(repo)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || (entry.file_name() != ".git"
+                    && !(entry.file_type().is_dir() && entry.path().join(".git").exists()))
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+fn dir_bytes(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Binary units with one decimal, e.g. `12.3 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
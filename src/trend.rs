@@ -0,0 +1,176 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::config_dir;
+use crate::{SECONDS_PER_DAY, Summary, unix_now};
+
+/// Totals of earlier scans, one JSON object per line, kept next to `config.toml`.
+const STATS_FILE: &str = "stats.jsonl";
+
+/// Scans older than this are dropped whenever a new one is recorded.
+const KEPT_DAYS: u64 = 90;
+
+/// Days shown in the dirty sparkline, today included.
+const SPARKLINE_DAYS: usize = 14;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ScanStats {
+    root: PathBuf,
+    scanned_at: u64,
+    total: usize,
+    dirty: usize,
+    repos_with_uncommitted: usize,
+    repos_with_unpushed: usize,
+    repos_without_remote: usize,
+}
+
+/// How this scan compares to an earlier one of the same root.
+pub struct Trend {
+    previous: ScanStats,
+    /// Whole days between the earlier scan and this one; 0 means earlier today.
+    days_ago: u64,
+    /// The last dirty count of each day with a scan, oldest first, ending with this scan.
+    dirty_by_day: Vec<usize>,
+}
+
+impl Trend {
+    /// `vs yesterday`, `vs 3 days ago` or `vs last scan`.
+    pub fn label(&self) -> String {
+        match self.days_ago {
+            0 => "vs last scan".to_string(),
+            1 => "vs yesterday".to_string(),
+            days => format!("vs {} days ago", days),
+        }
+    }
+
+    pub fn dirty(&self, now: usize) -> String {
+        indicator(now, self.previous.dirty)
+    }
+
+    pub fn uncommitted(&self, now: usize) -> String {
+        indicator(now, self.previous.repos_with_uncommitted)
+    }
+
+    pub fn unpushed(&self, now: usize) -> String {
+        indicator(now, self.previous.repos_with_unpushed)
+    }
+
+    pub fn without_remote(&self, now: usize) -> String {
+        indicator(now, self.previous.repos_without_remote)
+    }
+
+    /// A sparkline of dirty repos per day, or `None` with fewer than two days.
+    pub fn sparkline(&self) -> Option<String> {
+        if self.dirty_by_day.len() < 2 {
+            return None;
+        }
+        let max = self.dirty_by_day.iter().copied().max().unwrap_or(0).max(1);
+        Some(
+            self.dirty_by_day
+                .iter()
+                .map(|&dirty| SPARKS[dirty * (SPARKS.len() - 1) / max])
+                .collect(),
+        )
+    }
+}
+
+/// `▲2`, `▼2` or `=`; fewer is better for every counter we track.
+fn indicator(now: usize, before: usize) -> String {
+    match now.cmp(&before) {
+        std::cmp::Ordering::Greater => format!("▲{}", now - before),
+        std::cmp::Ordering::Less => format!("▼{}", before - now),
+        std::cmp::Ordering::Equal => "=".to_string(),
+    }
+}
+
+/// Appends this scan's totals and compares them with the latest scan of the same
+/// root from an earlier day, or the latest scan at all when there is none.
+pub fn record(root: &Path, summary: &Summary) -> io::Result<Option<Trend>> {
+    let path = config_dir()
+        .map(|dir| dir.join(STATS_FILE))
+        .ok_or_else(|| io::Error::other("cannot locate the config directory; set HOME"))?;
+    let root = fs::canonicalize(root)?;
+    let now = unix_now();
+    let cutoff = now.saturating_sub(KEPT_DAYS * SECONDS_PER_DAY);
+
+    let mut history: Vec<ScanStats> = load(&path)?
+        .into_iter()
+        .filter(|stats| stats.scanned_at >= cutoff)
+        .collect();
+    let current = ScanStats {
+        root,
+        scanned_at: now,
+        total: summary.total,
+        dirty: summary.dirty,
+        repos_with_uncommitted: summary.repos_with_uncommitted,
+        repos_with_unpushed: summary.repos_with_unpushed,
+        repos_without_remote: summary.repos_without_remote,
+    };
+    let trend = compare(&history, &current);
+    history.push(current);
+    save(&path, &history)?;
+    Ok(trend)
+}
+
+fn compare(history: &[ScanStats], current: &ScanStats) -> Option<Trend> {
+    let today = current.scanned_at / SECONDS_PER_DAY;
+    let earlier: Vec<&ScanStats> = history
+        .iter()
+        .filter(|stats| stats.root == current.root && stats.scanned_at <= current.scanned_at)
+        .collect();
+    let previous = earlier
+        .iter()
+        .rev()
+        .find(|stats| stats.scanned_at / SECONDS_PER_DAY < today)
+        .or(earlier.last())?;
+
+    let mut dirty_by_day: Vec<(u64, usize)> = Vec::new();
+    for stats in earlier.iter().copied().chain([current]) {
+        let day = stats.scanned_at / SECONDS_PER_DAY;
+        match dirty_by_day.last_mut() {
+            Some((last, dirty)) if *last == day => *dirty = stats.dirty,
+            _ => dirty_by_day.push((day, stats.dirty)),
+        }
+    }
+    let skip = dirty_by_day.len().saturating_sub(SPARKLINE_DAYS);
+
+    Some(Trend {
+        days_ago: today.saturating_sub(previous.scanned_at / SECONDS_PER_DAY),
+        previous: (*previous).clone(),
+        dirty_by_day: dirty_by_day
+            .into_iter()
+            .skip(skip)
+            .map(|(_, dirty)| dirty)
+            .collect(),
+    })
+}
+
+fn load(path: &Path) -> io::Result<Vec<ScanStats>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    // A line we cannot parse costs one data point, not the whole history.
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn save(path: &Path, history: &[ScanStats]) -> io::Result<()> {
+    let mut contents = String::new();
+    for stats in history {
+        contents.push_str(&serde_json::to_string(stats).map_err(io::Error::other)?);
+        contents.push('\n');
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
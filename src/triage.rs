@@ -0,0 +1,282 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::Config;
+use crate::git::{get_repo_status, run_git};
+use crate::redact::Redactor;
+use crate::{RepoStatus, ScanOptions, TriageArgs, overrides, scan_root};
+
+/// Changed files listed before each prompt; the rest are counted.
+const FILES_SHOWN: usize = 20;
+
+const HELP: [&str; 7] = [
+    "s  open a shell in the repository",
+    "e  open $VISUAL or $EDITOR in the repository",
+    "t  stash all changes, untracked files included",
+    "c  commit all changes with a message",
+    "p  push the current branch",
+    "n  leave it for now (also Enter)",
+    "q  stop triaging",
+];
+
+/// What became of one dirty repository.
+enum Outcome {
+    Clean,
+    Skipped,
+    Quit,
+}
+
+/// Walks the dirty repos one at a time and runs the chosen git actions in each,
+/// re-checking after every action until the repo is clean or skipped.
+pub fn run(
+    args: &TriageArgs,
+    config: &Config,
+    options: &ScanOptions,
+    redactor: Option<&Redactor>,
+) -> io::Result<()> {
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::other("triage needs an interactive terminal"));
+    }
+    let mut scan = scan_root(&args.root, options);
+    overrides::apply(&config.repos, &mut scan.statuses);
+    for error in &scan.errors {
+        eprintln!(
+            "error: {}: {}",
+            shown(&error.path, redactor).display(),
+            shown_message(&error.message, redactor)
+        );
+    }
+
+    let dirty: Vec<RepoStatus> = scan
+        .statuses
+        .into_iter()
+        .filter(|status| status.is_dirty && !status.is_bare)
+        .collect();
+    if dirty.is_empty() {
+        println!("no repositories with local changes found");
+        return Ok(());
+    }
+
+    let mut cleaned = 0;
+    let mut triaged = 0;
+    for (index, status) in dirty.iter().enumerate() {
+        println!();
+        println!(
+            "[{}/{}] {}",
+            index + 1,
+            dirty.len(),
+            shown(&status.path, redactor).display()
+        );
+        triaged += 1;
+        match triage_repo(status.clone(), options, redactor)? {
+            Outcome::Clean => cleaned += 1,
+            Outcome::Skipped => {}
+            Outcome::Quit => break,
+        }
+    }
+
+    println!();
+    println!(
+        "triaged {} of {} dirty repositories, {} now clean",
+        triaged,
+        dirty.len(),
+        cleaned
+    );
+    Ok(())
+}
+
+fn triage_repo(
+    mut status: RepoStatus,
+    options: &ScanOptions,
+    redactor: Option<&Redactor>,
+) -> io::Result<Outcome> {
+    let repo = status.path.clone();
+    loop {
+        print_status(&status, redactor);
+        let Some(choice) = prompt("[s]hell [e]ditor s[t]ash [c]ommit [p]ush [n]ext [q]uit? ")?
+        else {
+            return Ok(Outcome::Quit);
+        };
+        let result = match choice.as_str() {
+            "s" => open_shell(&repo),
+            "e" => open_editor(&repo),
+            "t" => git(
+                &repo,
+                &[
+                    "stash",
+                    "push",
+                    "--include-untracked",
+                    "-m",
+                    "gittracker triage",
+                ],
+            ),
+            // `git add --all` would mark conflicted files resolved, markers and all.
+            "c" if status.conflicts > 0 => Err(io::Error::other(format!(
+                "resolve the {} conflicted files first, e.g. from the shell",
+                status.conflicts
+            ))),
+            "c" => match prompt("commit message (empty to cancel): ")? {
+                Some(message) if !message.is_empty() => git(&repo, &["add", "--all"])
+                    .and_then(|()| git(&repo, &["commit", "-m", &message])),
+                _ => continue,
+            },
+            "p" => push(&status),
+            "" | "n" => return Ok(Outcome::Skipped),
+            "q" => return Ok(Outcome::Quit),
+            _ => {
+                for line in HELP {
+                    println!("  {}", line);
+                }
+                continue;
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("error: {}", err);
+        }
+
+        // Whatever ran may have changed anything, including outside this tool.
+        status = match get_repo_status(&repo, options) {
+            Ok(status) => status,
+            Err(err) => {
+                eprintln!(
+                    "error: {}: {}",
+                    shown(&repo, redactor).display(),
+                    shown_message(&err.message, redactor)
+                );
+                return Ok(Outcome::Skipped);
+            }
+        };
+        if !status.is_dirty {
+            println!("clean: {}", shown(&repo, redactor).display());
+            return Ok(Outcome::Clean);
+        }
+    }
+}
+
+fn print_status(status: &RepoStatus, redactor: Option<&Redactor>) {
+    let branch = match (&status.branch, redactor) {
+        (Some(branch), Some(redactor)) => redactor.redact_branch(branch),
+        (Some(branch), None) => branch.clone(),
+        (None, _) => "(detached)".to_string(),
+    };
+    println!(
+        "branch: {}, uncommitted: {} files, unpushed: {} commits{}",
+        branch,
+        status.uncommitted_changes,
+        status.unpushed_commits,
+        if status.has_upstream {
+            ""
+        } else {
+            ", upstream: none"
+        }
+    );
+    let Ok(changes) = run_git(&status.path, &["status", "--short"]) else {
+        return;
+    };
+    let lines: Vec<&str> = changes.lines().collect();
+    for line in lines.iter().take(FILES_SHOWN) {
+        // Short status lines are two status letters, a space and the path.
+        match (redactor, line.get(..3), line.get(3..)) {
+            (Some(redactor), Some(code), Some(path)) => println!(
+                "  {}{}",
+                code,
+                redactor.redact_path(Path::new(path)).display()
+            ),
+            _ => println!("  {}", line),
+        }
+    }
+    if lines.len() > FILES_SHOWN {
+        println!("  ... and {} more", lines.len() - FILES_SHOWN);
+    }
+}
+
+/// The path as printed, hashed under `--redact`.
+fn shown(path: &Path, redactor: Option<&Redactor>) -> PathBuf {
+    match redactor {
+        Some(redactor) => redactor.redact_path(path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Error messages quote paths and git output, so `--redact` hides them whole.
+fn shown_message<'a>(message: &'a str, redactor: Option<&Redactor>) -> &'a str {
+    if redactor.is_some() {
+        "[redacted]"
+    } else {
+        message
+    }
+}
+
+/// Reads one trimmed line; `None` at end of input.
+fn prompt(question: &str) -> io::Result<Option<String>> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+/// Pushes to the upstream, or sets one up on `origin` (else the first remote).
+fn push(status: &RepoStatus) -> io::Result<()> {
+    if status.has_upstream {
+        return git(&status.path, &["push"]);
+    }
+    let remote = status
+        .remotes
+        .iter()
+        .find(|name| *name == "origin")
+        .or(status.remotes.first())
+        .ok_or_else(|| io::Error::other("no remote to push to; add one from the shell"))?;
+    git(&status.path, &["push", "--set-upstream", remote, "HEAD"])
+}
+
+/// Runs git with the terminal attached, so hooks, editors and credential prompts work.
+fn git(repo: &Path, args: &[&str]) -> io::Result<()> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed ({})",
+            args.join(" "),
+            status
+        )));
+    }
+    Ok(())
+}
+
+fn open_shell(repo: &Path) -> io::Result<()> {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| {
+        if cfg!(windows) {
+            "cmd".to_string()
+        } else {
+            "sh".to_string()
+        }
+    });
+    println!("exit the shell to come back to triage");
+    Command::new(shell).current_dir(repo).status()?;
+    Ok(())
+}
+
+/// `$VISUAL`, then `$EDITOR`, which may carry arguments such as `code --wait`.
+fn open_editor(repo: &Path) -> io::Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::other("$EDITOR is empty"))?;
+    Command::new(program)
+        .args(words)
+        .arg(".")
+        .current_dir(repo)
+        .status()?;
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::git::{self, output_with_timeout};
+use crate::{ChangedFile, RepoStatus, ScanError, ScanErrorKind, ScanOptions, cloud, hg, jj};
+
+/// A version control system whose repositories the scan can find and inspect.
+pub trait Vcs: Sync {
+    /// Folder in a repository's root that marks it, e.g. `.git`.
+    fn marker(&self) -> &'static str;
+
+    fn status(&self, repo_root: &Path, options: &ScanOptions) -> Result<RepoStatus, ScanError>;
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum VcsKind {
+    #[default]
+    Git,
+    /// Jujutsu, including repos colocated with git
+    Jj,
+    /// Mercurial
+    Hg,
+}
+
+/// Which system reports a folder holding several markers: jj manages the git repo it is
+/// colocated with, so its view of the working copy is the one that counts.
+const PRECEDENCE: [VcsKind; 3] = [VcsKind::Jj, VcsKind::Hg, VcsKind::Git];
+
+impl VcsKind {
+    pub fn backend(self) -> &'static dyn Vcs {
+        match self {
+            VcsKind::Git => &Git,
+            VcsKind::Jj => &jj::Jujutsu,
+            VcsKind::Hg => &hg::Mercurial,
+        }
+    }
+}
+
+/// `--vcs` as given, or git alone when nothing was chosen.
+pub fn enabled(options: &ScanOptions) -> &[VcsKind] {
+    if options.vcs.is_empty() {
+        &[VcsKind::Git]
+    } else {
+        &options.vcs
+    }
+}
+
+/// Whether the name is any system's marker, enabled or not; their contents are
+/// internals and never worth walking.
+pub fn is_marker_name(name: &OsStr) -> bool {
+    PRECEDENCE
+        .iter()
+        .any(|kind| name == OsStr::new(kind.backend().marker()))
+}
+
+/// The enabled system that reports the repository rooted at `repo_root`, if any.
+pub fn detect(repo_root: &Path, enabled: &[VcsKind]) -> Option<VcsKind> {
+    PRECEDENCE.into_iter().find(|kind| {
+        let marker = repo_root.join(kind.backend().marker());
+        // Worktrees and submodules use a `.git` file rather than a folder.
+        enabled.contains(kind) && (marker.is_dir() || (*kind == VcsKind::Git && marker.is_file()))
+    })
+}
+
+struct Git;
+
+impl Vcs for Git {
+    fn marker(&self) -> &'static str {
+        ".git"
+    }
+
+    fn status(&self, repo_root: &Path, options: &ScanOptions) -> Result<RepoStatus, ScanError> {
+        git::get_repo_status(repo_root, options)
+    }
+}
+
+/// A status with nothing to report yet, for systems to fill in what they know.
+pub fn empty_status(repo_root: &Path, vcs: VcsKind) -> RepoStatus {
+    RepoStatus {
+        path: repo_root.to_path_buf(),
+        vcs,
+        is_dirty: false,
+        is_bare: false,
+        uncommitted_changes: 0,
+        conflicts: 0,
+        untracked_files: 0,
+        files: Vec::new(),
+        unpushed_commits: 0,
+        behind_commits: 0,
+        stashes: 0,
+        score: 0.0,
+        has_upstream: false,
+        branch: None,
+        last_commit: None,
+        last_touched: None,
+        remotes: Vec::new(),
+        remote: None,
+        enrichment: None,
+        remote_ahead: Vec::new(),
+        policy_violations: Vec::new(),
+        sizes: None,
+        dependency_drift: Vec::new(),
+        lfs: None,
+        sync_service: cloud::sync_service(repo_root),
+        volume: None,
+        metadata: BTreeMap::new(),
+        findings: BTreeMap::new(),
+        porcelain_v1: false,
+        parent: None,
+    }
+}
+
+/// The `--files` list from `jj diff --summary` or `hg status` output, whose lines are
+/// a one-letter code, a space and the path.
+pub fn changed_files(changes: &str, options: &ScanOptions) -> Vec<ChangedFile> {
+    let Some(max_files) = options.max_files else {
+        return Vec::new();
+    };
+    changes
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(code, path)| ChangedFile {
+            code: code.to_string(),
+            path: PathBuf::from(path),
+        })
+        .take(max_files)
+        .collect()
+}
+
+/// Runs a jj or hg command in the repo and returns its stdout. Failures keep the
+/// tool's own message, and a timeout is reported as such.
+pub fn run(
+    mut command: Command,
+    repo_root: &Path,
+    options: &ScanOptions,
+) -> Result<String, ScanError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    command.current_dir(repo_root);
+    let error = |kind, message| ScanError {
+        path: repo_root.to_path_buf(),
+        kind,
+        message,
+    };
+    match output_with_timeout(command, options.timeout) {
+        Ok(output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(output) => Err(error(
+            ScanErrorKind::Git,
+            format!(
+                "{} failed ({}): {}",
+                program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        )),
+        Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+            Err(error(ScanErrorKind::Timeout, err.to_string()))
+        }
+        Err(err) => Err(error(
+            ScanErrorKind::Git,
+            format!("failed to run {}: {}", program, err),
+        )),
+    }
+}
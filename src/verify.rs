@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::git::{run_git, run_git_with_timeout};
+use crate::{ScanOptions, VerifyArgs, scan_root};
+
+/// What the remote says about a branch that looks pushed locally.
+enum BranchCheck {
+    Ok,
+    Missing,
+    Rewritten(String),
+    Unknown(String),
+}
+
+struct TrackedBranch {
+    name: String,
+    tip: String,
+    remote: String,
+    remote_ref: String,
+}
+
+pub fn run(args: &VerifyArgs, options: &ScanOptions) -> io::Result<()> {
+    let scan = scan_root(&args.root, options);
+    let mut checked = 0;
+    let mut problems = 0;
+
+    for error in &scan.errors {
+        println!("error: {} ({})", error.path.display(), error.message);
+    }
+    for status in &scan.statuses {
+        let branches = match pushed_branches(&status.path) {
+            Ok(branches) => branches,
+            Err(err) => {
+                println!("error: {} ({})", status.path.display(), err);
+                continue;
+            }
+        };
+
+        let mut remote_heads: HashMap<String, io::Result<HashMap<String, String>>> = HashMap::new();
+        for branch in branches {
+            let heads = remote_heads
+                .entry(branch.remote.clone())
+                .or_insert_with(|| ls_remote(&status.path, &branch.remote, options));
+            let heads = match heads {
+                Ok(heads) => heads,
+                Err(err) => {
+                    println!(
+                        "unreachable: {} {} ({})",
+                        status.path.display(),
+                        branch.remote,
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            checked += 1;
+            match check_branch(&status.path, &branch, heads) {
+                BranchCheck::Ok => {}
+                BranchCheck::Missing => {
+                    problems += 1;
+                    println!(
+                        "missing: {} {} (not on {})",
+                        status.path.display(),
+                        branch.name,
+                        branch.remote
+                    );
+                }
+                BranchCheck::Rewritten(remote_tip) => {
+                    problems += 1;
+                    println!(
+                        "rewritten: {} {} ({} no longer contains {})",
+                        status.path.display(),
+                        branch.name,
+                        short(&remote_tip),
+                        short(&branch.tip)
+                    );
+                }
+                BranchCheck::Unknown(remote_tip) => {
+                    println!(
+                        "unverified: {} {} (remote moved to {}; fetch to verify)",
+                        status.path.display(),
+                        branch.name,
+                        short(&remote_tip)
+                    );
+                }
+            }
+        }
+    }
+
+    println!(
+        "verified {} pushed branches in {} repositories, {} not safe on the remote",
+        checked,
+        scan.statuses.len(),
+        problems
+    );
+    if problems > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Local branches whose upstream tracking says nothing is left to push.
+fn pushed_branches(repo: &Path) -> io::Result<Vec<TrackedBranch>> {
+    let refs = run_git(
+        repo,
+        &[
+            "for-each-ref",
+            "--format=%(refname:short)%09%(objectname)%09%(upstream:remotename)%09%(upstream:remoteref)%09%(upstream:track)",
+            "refs/heads",
+        ],
+    )?;
+
+    let mut branches = Vec::new();
+    for line in refs.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [name, tip, remote, remote_ref, track] = fields[..] else {
+            continue;
+        };
+        // `remote = .` tracks another local branch; there's nothing remote to verify.
+        if remote.is_empty() || remote == "." || remote_ref.is_empty() || track.contains("ahead") {
+            continue;
+        }
+
+        branches.push(TrackedBranch {
+            name: name.to_string(),
+            tip: tip.to_string(),
+            remote: remote.to_string(),
+            remote_ref: remote_ref.to_string(),
+        });
+    }
+    Ok(branches)
+}
+
+fn ls_remote(
+    repo: &Path,
+    remote: &str,
+    options: &ScanOptions,
+) -> io::Result<HashMap<String, String>> {
+    let output = run_git_with_timeout(repo, &["ls-remote", "--heads", remote], options.timeout)?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(hash, reference)| (reference.to_string(), hash.to_string()))
+        .collect())
+}
+
+fn check_branch(
+    repo: &Path,
+    branch: &TrackedBranch,
+    heads: &HashMap<String, String>,
+) -> BranchCheck {
+    let Some(remote_tip) = heads.get(&branch.remote_ref) else {
+        return BranchCheck::Missing;
+    };
+    if *remote_tip == branch.tip {
+        return BranchCheck::Ok;
+    }
+
+    // The remote moved; the tip is only safe if it is still part of the remote history.
+    if run_git(
+        repo,
+        &["cat-file", "-e", &format!("{}^{{commit}}", remote_tip)],
+    )
+    .is_err()
+    {
+        return BranchCheck::Unknown(remote_tip.clone());
+    }
+    match run_git(
+        repo,
+        &["merge-base", "--is-ancestor", &branch.tip, remote_tip],
+    ) {
+        Ok(_) => BranchCheck::Ok,
+        Err(_) => BranchCheck::Rewritten(remote_tip.clone()),
+    }
+}
+
+fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(10)]
+}
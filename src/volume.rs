@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::RepoStatus;
+
+/// The mounted file system a repository lives on.
+#[derive(Clone, Debug, Serialize)]
+pub struct Volume {
+    pub mount_point: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filesystem: Option<String>,
+    /// `None` when the platform or the volume type gives no reliable answer.
+    pub is_encrypted: Option<bool>,
+}
+
+/// Finds the volume of every local repo; repos reached through `--docker`/`--ssh` are
+/// skipped. Each volume is only inspected once.
+pub fn audit(statuses: &mut [RepoStatus]) {
+    let mounts = platform::mounts();
+    let mut volumes: HashMap<PathBuf, Option<Volume>> = HashMap::new();
+    for status in statuses {
+        let Ok(path) = std::fs::canonicalize(&status.path) else {
+            continue;
+        };
+        let Some(mount_point) = platform::mount_point(&mounts, &path) else {
+            continue;
+        };
+        status.volume = volumes
+            .entry(mount_point.clone())
+            .or_insert_with(|| platform::inspect(&mounts, &mount_point))
+            .clone();
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::Volume;
+
+    /// File systems that encrypt file contents themselves, whatever device is below.
+    const ENCRYPTING_FILESYSTEMS: [&str; 3] = ["ecryptfs", "fuse.gocryptfs", "fuse.cryfs"];
+
+    pub struct Mount {
+        mount_point: PathBuf,
+        /// `major:minor` of the backing device.
+        device: String,
+        filesystem: String,
+    }
+
+    pub fn mounts() -> Vec<Mount> {
+        let Ok(mountinfo) = fs::read_to_string("/proc/self/mountinfo") else {
+            return Vec::new();
+        };
+        // `id parent major:minor root mount-point options [optional...] - fstype source ...`
+        mountinfo
+            .lines()
+            .filter_map(|line| {
+                let (mount, fs) = line.split_once(" - ")?;
+                let fields: Vec<&str> = mount.split(' ').collect();
+                Some(Mount {
+                    mount_point: PathBuf::from(unescape(fields.get(4)?)),
+                    device: fields.get(2)?.to_string(),
+                    filesystem: fs.split(' ').next()?.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// The innermost mount containing the path.
+    pub fn mount_point(mounts: &[Mount], path: &Path) -> Option<PathBuf> {
+        mounts
+            .iter()
+            .filter(|mount| path.starts_with(&mount.mount_point))
+            .max_by_key(|mount| mount.mount_point.components().count())
+            .map(|mount| mount.mount_point.clone())
+    }
+
+    pub fn inspect(mounts: &[Mount], mount_point: &Path) -> Option<Volume> {
+        // Later entries shadow earlier ones mounted on the same point.
+        let mount = mounts
+            .iter()
+            .rev()
+            .find(|mount| mount.mount_point == mount_point)?;
+        let is_encrypted = if ENCRYPTING_FILESYSTEMS.contains(&mount.filesystem.as_str()) {
+            Some(true)
+        } else if mount.device.starts_with("0:") {
+            // Anonymous devices back tmpfs, overlays and network shares.
+            None
+        } else {
+            Some(is_crypt_device(
+                &PathBuf::from("/sys/dev/block").join(&mount.device),
+            ))
+        };
+        Some(Volume {
+            mount_point: mount.mount_point.clone(),
+            filesystem: Some(mount.filesystem.clone()),
+            is_encrypted,
+        })
+    }
+
+    /// A dm-crypt mapping, or a device stacked on one, such as LVM on LUKS.
+    fn is_crypt_device(device: &Path) -> bool {
+        let uuid = fs::read_to_string(device.join("dm/uuid")).unwrap_or_default();
+        if uuid.starts_with("CRYPT-") {
+            return true;
+        }
+        // Partitions have no `slaves` of their own; their disk does not either.
+        let Ok(slaves) = fs::read_dir(device.join("slaves")) else {
+            return false;
+        };
+        slaves
+            .filter_map(Result::ok)
+            .any(|slave| is_crypt_device(&slave.path()))
+    }
+
+    /// Mount points escape spaces, tabs, newlines and backslashes as octal.
+    fn unescape(value: &str) -> String {
+        value
+            .replace("\\040", " ")
+            .replace("\\011", "\t")
+            .replace("\\012", "\n")
+            .replace("\\134", "\\")
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use super::Volume;
+
+    pub struct Mount;
+
+    pub fn mounts() -> Vec<Mount> {
+        Vec::new()
+    }
+
+    pub fn mount_point(_: &[Mount], path: &Path) -> Option<PathBuf> {
+        let output = Command::new("df").arg("-P").arg(path).output().ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // `Filesystem 512-blocks Used Available Capacity Mounted on`; the mount point
+        // is everything after the fifth column and may contain spaces.
+        let line = stdout.lines().nth(1)?;
+        let mount_point = line
+            .split_whitespace()
+            .skip(5)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(PathBuf::from(mount_point))
+    }
+
+    pub fn inspect(_: &[Mount], mount_point: &Path) -> Option<Volume> {
+        let output = Command::new("diskutil")
+            .arg("info")
+            .arg(mount_point)
+            .output()
+            .ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let field = |name: &str| {
+            stdout
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(key, _)| key.trim() == name)
+                .map(|(_, value)| value.trim().to_string())
+        };
+        Some(Volume {
+            mount_point: mount_point.to_path_buf(),
+            filesystem: field("File System Personality"),
+            is_encrypted: field("FileVault").map(|value| value == "Yes"),
+        })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::path::{Component, Path, PathBuf};
+    use std::process::Command;
+
+    use super::Volume;
+
+    pub struct Mount;
+
+    pub fn mounts() -> Vec<Mount> {
+        Vec::new()
+    }
+
+    /// The drive, e.g. `C:\`.
+    pub fn mount_point(_: &[Mount], path: &Path) -> Option<PathBuf> {
+        match path.components().next()? {
+            Component::Prefix(prefix) => {
+                let drive = prefix.as_os_str().to_string_lossy();
+                let drive = drive.trim_start_matches(r"\\?\");
+                Some(PathBuf::from(format!(r"{}\", drive)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn inspect(_: &[Mount], mount_point: &Path) -> Option<Volume> {
+        let drive = mount_point
+            .to_string_lossy()
+            .trim_end_matches('\\')
+            .to_string();
+        let field = |program: &str, args: &[&str], name: &str| {
+            let output = Command::new(program).args(args).output().ok()?;
+            let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+            stdout
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(key, _)| key.trim() == name)
+                .map(|(_, value)| value.trim().to_string())
+        };
+        // `manage-bde` needs an elevated prompt; without one, encryption stays unknown.
+        let protection = field("manage-bde", &["-status", &drive], "Protection Status");
+        Some(Volume {
+            mount_point: mount_point.to_path_buf(),
+            filesystem: field(
+                "fsutil",
+                &["fsinfo", "volumeinfo", &drive],
+                "File System Name",
+            ),
+            is_encrypted: protection.map(|status| status == "Protection On"),
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use std::path::{Path, PathBuf};
+
+    use super::Volume;
+
+    pub struct Mount;
+
+    pub fn mounts() -> Vec<Mount> {
+        Vec::new()
+    }
+
+    pub fn mount_point(_: &[Mount], _: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    pub fn inspect(_: &[Mount], _: &Path) -> Option<Volume> {
+        None
+    }
+}
+
+/// `ext4 at /home`, or just the mount point when the file system is unknown.
+pub fn describe(volume: &Volume) -> String {
+    match &volume.filesystem {
+        Some(filesystem) => format!("{} at {}", filesystem, volume.mount_point.display()),
+        None => volume.mount_point.display().to_string(),
+    }
+}
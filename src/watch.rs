@@ -0,0 +1,497 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use ignore::gitignore::Gitignore;
+use notify::{Event, RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::config::Config;
+use crate::events::{build_exclusions, relevant_paths};
+use crate::notify::Notifier;
+use crate::policy::PolicyEngine;
+use crate::redact::Redactor;
+use crate::{
+    RepoStatus, ScanError, ScanOptions, ScanResult, WatchArgs, WatchFormat, apply_config, scan_root,
+};
+
+/// What a batch of filesystem events asks for.
+#[derive(Default)]
+struct Pending {
+    repos: BTreeSet<PathBuf>,
+    /// An event outside every known repo, which may be a repository being created or removed.
+    should_rescan: bool,
+}
+
+struct Watch<'a> {
+    root: PathBuf,
+    options: &'a ScanOptions,
+    config: &'a Config,
+    policies: &'a PolicyEngine,
+    redactor: Option<&'a Redactor>,
+    cooldown: Duration,
+    repos: Vec<RepoStatus>,
+    /// When each repo, or the root for full rescans, was last checked.
+    checked_at: HashMap<PathBuf, Instant>,
+    format: WatchFormat,
+    /// With `--format jsonl`, the repos as consumers know them from earlier patches.
+    published: Map<String, Value>,
+    notifier: Option<Notifier>,
+}
+
+/// One operation of an RFC 6902 patch.
+#[derive(Serialize)]
+struct PatchOp {
+    op: &'static str,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<Value>,
+}
+
+pub fn run(
+    args: &WatchArgs,
+    config: &Config,
+    policies: &PolicyEngine,
+    options: &ScanOptions,
+    redactor: Option<&Redactor>,
+) -> io::Result<()> {
+    let absolute_root = std::path::absolute(&args.root)?;
+    let exclusions = build_exclusions(&absolute_root, &config.watch.exclude)?;
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+    watcher
+        .watch(&absolute_root, RecursiveMode::Recursive)
+        .map_err(io::Error::other)?;
+
+    let mut watch = Watch {
+        root: args.root.clone(),
+        options,
+        config,
+        policies,
+        redactor,
+        cooldown: config.watch.cooldown,
+        repos: Vec::new(),
+        checked_at: HashMap::new(),
+        format: args.format,
+        published: Map::new(),
+        notifier: args.notify.then(|| Notifier::new(args.notify_stale_after)),
+    };
+    watch.rescan();
+    eprintln!(
+        "watching {} repositories under {}",
+        watch.repos.len(),
+        args.root.display()
+    );
+
+    let mut pending = Pending::default();
+    let mut last_event: Option<Instant> = None;
+    loop {
+        let received = match last_event {
+            None => events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(last) => {
+                events.recv_timeout(watch.next_wait(&pending, last, config.watch.debounce))
+            }
+        };
+        match received {
+            Ok(Ok(event)) => {
+                if watch.queue(&event, &absolute_root, &exclusions, &mut pending) {
+                    last_event = Some(Instant::now());
+                }
+            }
+            Ok(Err(err)) => eprintln!("error: watch failed: {}", err),
+            Err(RecvTimeoutError::Timeout) => {
+                watch.flush(&mut pending);
+                if pending.repos.is_empty() && !pending.should_rescan {
+                    last_event = None;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+impl Watch<'_> {
+    /// Records which repos an event touches; returns false when no path was relevant.
+    fn queue(
+        &self,
+        event: &Event,
+        absolute_root: &Path,
+        exclusions: &Gitignore,
+        pending: &mut Pending,
+    ) -> bool {
+        let mut is_relevant = false;
+        for relative in relevant_paths(event, absolute_root, exclusions) {
+            is_relevant = true;
+            let path = self.root.join(relative);
+            match self.enclosing_repo(&path) {
+                Some(repo) => {
+                    pending.repos.insert(repo.to_path_buf());
+                }
+                None => pending.should_rescan = true,
+            }
+        }
+        is_relevant
+    }
+
+    /// The innermost known repository containing the path.
+    fn enclosing_repo(&self, path: &Path) -> Option<&Path> {
+        self.repos
+            .iter()
+            .map(|status| status.path.as_path())
+            .filter(|repo| path.starts_with(repo))
+            .max_by_key(|repo| repo.components().count())
+    }
+
+    /// How long to wait before the next flush: the rest of the debounce window, or
+    /// until the first repo held back by its cooldown may be checked again.
+    fn next_wait(&self, pending: &Pending, last_event: Instant, debounce: Duration) -> Duration {
+        let quiet = debounce.saturating_sub(last_event.elapsed());
+        if !quiet.is_zero() {
+            return quiet;
+        }
+
+        let rescan_key = pending.should_rescan.then_some(self.root.as_path());
+        pending
+            .repos
+            .iter()
+            .map(PathBuf::as_path)
+            .chain(rescan_key)
+            .map(|key| self.cooldown_left(key))
+            .min()
+            .unwrap_or_default()
+    }
+
+    fn cooldown_left(&self, key: &Path) -> Duration {
+        self.checked_at
+            .get(key)
+            .map(|checked| self.cooldown.saturating_sub(checked.elapsed()))
+            .unwrap_or_default()
+    }
+
+    /// Re-checks everything whose cooldown has passed; the rest stays pending.
+    fn flush(&mut self, pending: &mut Pending) {
+        if pending.should_rescan && self.cooldown_left(&self.root).is_zero() {
+            pending.should_rescan = false;
+            pending.repos.clear();
+            self.rescan();
+            return;
+        }
+
+        let ready: Vec<PathBuf> = pending
+            .repos
+            .iter()
+            .filter(|repo| self.cooldown_left(repo).is_zero())
+            .cloned()
+            .collect();
+        for repo in ready {
+            pending.repos.remove(&repo);
+            self.recheck(&repo);
+        }
+    }
+
+    fn rescan(&mut self) {
+        let is_initial = self.checked_at.is_empty();
+        self.checked_at.insert(self.root.clone(), Instant::now());
+        let mut scan = scan_root(&self.root, self.options);
+        apply_config(
+            &mut scan.statuses,
+            self.config,
+            self.policies,
+            self.options,
+            true,
+        );
+
+        let mut changes = ScanResult {
+            errors: scan.errors,
+            ..ScanResult::default()
+        };
+        let mut removed: Vec<PathBuf> = Vec::new();
+        for old in &self.repos {
+            if !scan.statuses.iter().any(|new| new.path == old.path) {
+                removed.push(old.path.clone());
+            }
+        }
+        for new in &scan.statuses {
+            let old = self.repos.iter().find(|old| old.path == new.path);
+            // The first text report only lists dirty repos; a patch stream needs them all.
+            let is_listed = !is_initial || new.is_dirty || self.format == WatchFormat::Jsonl;
+            if old.is_none_or(|old| has_changed(old, new)) && is_listed {
+                changes.statuses.push(new.clone());
+            }
+        }
+
+        let now = Instant::now();
+        for status in &scan.statuses {
+            self.checked_at.insert(status.path.clone(), now);
+        }
+        self.repos = scan.statuses;
+        self.notify();
+        self.report(changes, removed);
+    }
+
+    fn recheck(&mut self, repo: &Path) {
+        self.checked_at.insert(repo.to_path_buf(), Instant::now());
+        let mut changes = ScanResult::default();
+        let mut removed = Vec::new();
+        let Some(index) = self.repos.iter().position(|status| status.path == repo) else {
+            return;
+        };
+
+        match self.repos[index].vcs.backend().status(repo, self.options) {
+            Ok(status) => {
+                let mut statuses = vec![RepoStatus {
+                    parent: self.repos[index].parent.clone(),
+                    ..status
+                }];
+                apply_config(
+                    &mut statuses,
+                    self.config,
+                    self.policies,
+                    self.options,
+                    true,
+                );
+                // Only ignored repos are dropped, and a rescan never lists those.
+                let Some(status) = statuses.pop() else {
+                    return;
+                };
+                if has_changed(&self.repos[index], &status) {
+                    changes.statuses.push(status.clone());
+                }
+                self.repos[index] = status;
+            }
+            Err(_) if !repo.exists() => {
+                self.repos.remove(index);
+                removed.push(repo.to_path_buf());
+            }
+            Err(err) => changes.errors.push(err),
+        }
+        self.notify();
+        self.report(changes, removed);
+    }
+
+    /// Hands the notifier every known repo, which it compares with what it saw last.
+    fn notify(&mut self) {
+        if let Some(notifier) = &mut self.notifier {
+            notifier.observe(&self.repos);
+        }
+    }
+
+    fn report(&mut self, mut changes: ScanResult, mut removed: Vec<PathBuf>) {
+        if let Some(redactor) = self.redactor {
+            redactor.redact_scan(&mut changes);
+            for path in &mut removed {
+                *path = redactor.redact_path(path);
+            }
+        }
+
+        for ScanError { path, message, .. } in &changes.errors {
+            eprintln!("error: {} ({})", path.display(), message);
+        }
+        match self.format {
+            WatchFormat::Text => print_text(&changes.statuses, &removed),
+            WatchFormat::Jsonl => self.print_patch(&changes.statuses, &removed),
+        }
+    }
+
+    /// Prints the operations that bring the consumer's copy up to date.
+    fn print_patch(&mut self, statuses: &[RepoStatus], removed: &[PathBuf]) {
+        let ops = patch_ops(&mut self.published, statuses, removed);
+        if ops.is_empty() {
+            return;
+        }
+        match serde_json::to_string(&ops) {
+            Ok(line) => println!("{}", line),
+            Err(err) => eprintln!("error: failed to encode patch: {}", err),
+        }
+    }
+}
+
+/// The operations that bring the consumer's copy in `published` up to date, skipping
+/// anything it already has so a patch never repeats a value.
+fn patch_ops(
+    published: &mut Map<String, Value>,
+    statuses: &[RepoStatus],
+    removed: &[PathBuf],
+) -> Vec<PatchOp> {
+    let mut ops = Vec::new();
+    for path in removed {
+        let key = path.to_string_lossy().into_owned();
+        if published.remove(&key).is_some() {
+            ops.push(PatchOp {
+                op: "remove",
+                path: pointer(&[&key]),
+                value: None,
+            });
+        }
+    }
+    for status in statuses {
+        let key = status.path.to_string_lossy().into_owned();
+        let Ok(value) = serde_json::to_value(status) else {
+            continue;
+        };
+        match (published.get(&key), &value) {
+            (Some(Value::Object(old)), Value::Object(new)) => {
+                for (field, new_value) in new {
+                    if old.get(field) == Some(new_value) {
+                        continue;
+                    }
+                    ops.push(PatchOp {
+                        op: if old.contains_key(field) {
+                            "replace"
+                        } else {
+                            "add"
+                        },
+                        path: pointer(&[&key, field]),
+                        value: Some(new_value.clone()),
+                    });
+                }
+                for field in old.keys().filter(|field| !new.contains_key(*field)) {
+                    ops.push(PatchOp {
+                        op: "remove",
+                        path: pointer(&[&key, field]),
+                        value: None,
+                    });
+                }
+            }
+            _ => ops.push(PatchOp {
+                op: "add",
+                path: pointer(&[&key]),
+                value: Some(value.clone()),
+            }),
+        }
+        published.insert(key, value);
+    }
+    ops
+}
+
+/// A JSON pointer below `/repos`, escaped as RFC 6901 requires.
+fn pointer(tokens: &[&str]) -> String {
+    let mut pointer = String::from("/repos");
+    for token in tokens {
+        pointer.push('/');
+        pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    }
+    pointer
+}
+
+fn print_text(statuses: &[RepoStatus], removed: &[PathBuf]) {
+    for status in statuses {
+        if status.is_dirty {
+            println!(
+                "dirty: {} (uncommitted: {} files, unpushed: {} commits)",
+                status.path.display(),
+                status.uncommitted_changes,
+                status.unpushed_commits
+            );
+        } else {
+            println!("clean: {}", status.path.display());
+        }
+    }
+    for path in removed {
+        println!("removed: {}", path.display());
+    }
+}
+
+fn has_changed(old: &RepoStatus, new: &RepoStatus) -> bool {
+    old.is_dirty != new.is_dirty
+        || old.uncommitted_changes != new.uncommitted_changes
+        || old.unpushed_commits != new.unpushed_commits
+        || old.has_upstream != new.has_upstream
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::vcs::{VcsKind, empty_status};
+
+    fn status(path: &str) -> RepoStatus {
+        empty_status(Path::new(path), VcsKind::Git)
+    }
+
+    /// Each operation as `(op, path, value)`.
+    fn ops(ops: Vec<PatchOp>) -> Vec<(&'static str, String, Option<Value>)> {
+        ops.into_iter()
+            .map(|op| (op.op, op.path, op.value))
+            .collect()
+    }
+
+    #[test]
+    fn pointer_escapes_tilde_and_slash() {
+        assert_eq!(pointer(&["/home/a~b"]), "/repos/~1home~1a~0b");
+        assert_eq!(pointer(&["~/x", "is_dirty"]), "/repos/~0~1x/is_dirty");
+        // `~1` in a path must not read back as `/`.
+        assert_eq!(pointer(&["a~1b"]), "/repos/a~01b");
+        assert_eq!(pointer(&[]), "/repos");
+    }
+
+    #[test]
+    fn adds_repos_the_consumer_has_not_seen() {
+        let mut published = Map::new();
+        let repo = status("/r");
+        let patch = ops(patch_ops(&mut published, std::slice::from_ref(&repo), &[]));
+        assert_eq!(
+            patch,
+            [(
+                "add",
+                "/repos/~1r".to_string(),
+                serde_json::to_value(&repo).ok()
+            )]
+        );
+        assert!(published.contains_key("/r"));
+    }
+
+    #[test]
+    fn patches_only_changed_fields() {
+        let mut published = Map::new();
+        let before = RepoStatus {
+            branch: Some("main".to_string()),
+            ..status("/r")
+        };
+        patch_ops(&mut published, &[before], &[]);
+
+        let after = RepoStatus {
+            is_dirty: true,
+            uncommitted_changes: 2,
+            last_touched: Some(7),
+            ..status("/r")
+        };
+        let mut patch = ops(patch_ops(&mut published, std::slice::from_ref(&after), &[]));
+        patch.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            patch,
+            [
+                ("remove", "/repos/~1r/branch".to_string(), None),
+                (
+                    "replace",
+                    "/repos/~1r/is_dirty".to_string(),
+                    Some(json!(true))
+                ),
+                ("add", "/repos/~1r/last_touched".to_string(), Some(json!(7))),
+                (
+                    "replace",
+                    "/repos/~1r/uncommitted_changes".to_string(),
+                    Some(json!(2))
+                ),
+            ]
+        );
+
+        // Nothing left to tell the consumer.
+        assert!(patch_ops(&mut published, &[after], &[]).is_empty());
+    }
+
+    #[test]
+    fn removes_only_published_repos() {
+        let mut published = Map::new();
+        patch_ops(&mut published, &[status("/r")], &[]);
+        let removed = [PathBuf::from("/r"), PathBuf::from("/never-seen")];
+        let patch = ops(patch_ops(&mut published, &[], &removed));
+        assert_eq!(patch, [("remove", "/repos/~1r".to_string(), None)]);
+        assert!(published.is_empty());
+    }
+}